@@ -1,7 +1,7 @@
 #!/bin/bash
 
 
-python scripts/ipfs-json-maker.py
+cargo run --release -- ipfs lod-data.json clouds/ipfs-lod.json --mirrored-only
 
 cargo run --release -- lod-data.json clouds/cross-domain-lod.svg -n 10 -i 5000 -c 350 --ident=neighbour --settings=clouds/cross-domain-lod.json
 
@@ -23,12 +23,10 @@ cargo run --release -- lod-data.json clouds/user-generated-lod.svg -n 10 -i 5000
 
 cargo run --release -- lod-data.json clouds/lod-cloud.svg -n 10 -i 5000 --ident=neighbour --settings=clouds/lod-cloud-settings.json
 
-cargo run --release -- ipfs-lod.json clouds/ipfs-lod.svg -n 10 -i 5000 -c 350 --ident=neighbour --settings=clouds/lod-cloud-settings.json
+cargo run --release -- clouds/ipfs-lod.json clouds/ipfs-lod.svg -n 10 -i 5000 -c 350 --ident=neighbour --settings=clouds/lod-cloud-settings.json
 
-cd clouds
-for f in *.svg 
+for f in clouds/*.svg 
 do 
-    inkscape -b white -d 300 -e ${f%.svg}.png $f
+    cargo run --release -- render $f ${f%.svg}.png --dpi 300 --background white
 done
-convert -scale 40% lod-cloud.png lod-cloud-sm.jpg
-cd -
+cargo run --release -- render clouds/lod-cloud.svg clouds/lod-cloud-sm.jpg --dpi 120
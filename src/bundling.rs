@@ -0,0 +1,201 @@
+//! Force-directed edge bundling (Holten and van Wijk, 2009), which bends
+//! links that run alongside each other into shared bundles, so that the
+//! main flows between the parts of a dense cloud can be seen. The links are
+//! split into points that attract the matching points of compatible links,
+//! while springs keep each link together
+type Point = (f64, f64);
+
+/// The number of cycles, each of which splits each link into twice as many
+/// parts
+const CYCLES : usize = 5;
+/// The number of iterations of the first cycle
+const ITERATIONS : usize = 90;
+/// The factor by which the iterations decrease from cycle to cycle
+const ITERATION_RATE : f64 = 2.0 / 3.0;
+/// The step of the first cycle, which halves from cycle to cycle
+const STEP : f64 = 0.1;
+/// The stiffness of the springs between the points of a link
+const STIFFNESS : f64 = 0.1;
+/// The lowest compatibility at which two links attract each other
+const COMPATIBILITY_THRESHOLD : f64 = 0.6;
+
+/// Bundle links, given by their start and end, with a strength from 0
+/// (straight links) to 1 (fully bundled links). The return value is the
+/// points each link passes through between its ends. Every pair of links is
+/// compared, so the time grows with the square of the number of links.
+pub fn bundle(links : &[(Point, Point)], strength : f64) -> Vec<Vec<Point>> {
+    let strength = strength.clamp(0.0, 1.0);
+    // The compatible links of each link, and whether they run the other way
+    let compatible : Vec<Vec<(usize, bool)>> = (0..links.len()).map(|i| {
+        (0..links.len()).filter(|&j| j != i && compatibility(links[i], links[j]) >= COMPATIBILITY_THRESHOLD)
+            .map(|j| (j, dot(vector(links[i]), vector(links[j])) < 0.0))
+            .collect()
+    }).collect();
+
+    let mut points : Vec<Vec<Point>> = links.iter().map(|&(s, e)| vec![midpoint(s, e)]).collect();
+    let mut step = STEP;
+    let mut iterations = ITERATIONS as f64;
+    for cycle in 0..CYCLES {
+        if cycle > 0 {
+            points = links.iter().zip(points.iter()).map(|(&(s, e), p)| subdivide(s, e, p)).collect();
+            step /= 2.0;
+            iterations *= ITERATION_RATE;
+        }
+        for _ in 0..iterations.round() as usize {
+            points = (0..links.len()).map(|i| move_points(i, links, &points, &compatible[i], step)).collect();
+        }
+    }
+
+    // A weaker bundling straightens the links towards their ends
+    links.iter().zip(points).map(|(&(s, e), p)| {
+        let n = p.len() as f64 + 1.0;
+        p.iter().enumerate().map(|(k, &(x, y))| {
+            let t = (k + 1) as f64 / n;
+            let straight = (s.0 + t * (e.0 - s.0), s.1 + t * (e.1 - s.1));
+            (straight.0 + strength * (x - straight.0), straight.1 + strength * (y - straight.1))
+        }).collect()
+    }).collect()
+}
+
+/// The next positions of the points of a link, pulled by the springs to
+/// their neighbours and attracted by the matching points of compatible links
+fn move_points(i : usize, links : &[(Point, Point)], points : &[Vec<Point>],
+               compatible : &[(usize, bool)], step : f64) -> Vec<Point> {
+    let (start, end) = links[i];
+    let p = &points[i];
+    let n = p.len();
+    let k = STIFFNESS / (distance(start, end).max(1.0) * (n + 1) as f64);
+    (0..n).map(|m| {
+        let (x, y) = p[m];
+        let previous = if m == 0 { start } else { p[m - 1] };
+        let next = if m + 1 == n { end } else { p[m + 1] };
+        let mut force = (k * (previous.0 - x + next.0 - x), k * (previous.1 - y + next.1 - y));
+        for &(j, reversed) in compatible {
+            let (qx, qy) = points[j][if reversed { n - 1 - m } else { m }];
+            let d = distance((x, y), (qx, qy));
+            if d > 1e-6 {
+                force.0 += (qx - x) / d;
+                force.1 += (qy - y) / d;
+            }
+        }
+        (x + step * force.0, y + step * force.1)
+    }).collect()
+}
+
+/// Double the points of a link, spread evenly along the polyline through
+/// them
+fn subdivide(start : Point, end : Point, points : &[Point]) -> Vec<Point> {
+    let mut polyline = vec![start];
+    polyline.extend_from_slice(points);
+    polyline.push(end);
+    let length : f64 = polyline.windows(2).map(|w| distance(w[0], w[1])).sum();
+    let count = points.len() * 2 + 1;
+    let segment = length / (count + 1) as f64;
+    let mut result = Vec::with_capacity(count);
+    let mut remaining = segment;
+    for w in polyline.windows(2) {
+        let (a, b) = (w[0], w[1]);
+        let d = distance(a, b);
+        let mut travelled = 0.0;
+        while d - travelled >= remaining && result.len() < count {
+            travelled += remaining;
+            let t = if d > 0.0 { travelled / d } else { 0.0 };
+            result.push((a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1)));
+            remaining = segment;
+        }
+        remaining -= d - travelled;
+    }
+    // Rounding may leave the last points short of the end
+    while result.len() < count {
+        result.push(*result.last().unwrap_or(&start));
+    }
+    result
+}
+
+/// How alike two links are in angle, length, position and how much each
+/// faces the other, from 0 to 1
+fn compatibility(p : (Point, Point), q : (Point, Point)) -> f64 {
+    let (lp, lq) = (distance(p.0, p.1), distance(q.0, q.1));
+    if lp == 0.0 || lq == 0.0 {
+        return 0.0;
+    }
+    let angle = (dot(vector(p), vector(q)) / (lp * lq)).abs();
+    let average = (lp + lq) / 2.0;
+    let scale = 2.0 / (average / lp.min(lq) + lp.max(lq) / average);
+    let position = average / (average + distance(midpoint(p.0, p.1), midpoint(q.0, q.1)));
+    angle * scale * position * visibility(p, q).min(visibility(q, p))
+}
+
+/// How much of a link faces the projection of another link onto it
+fn visibility(p : (Point, Point), q : (Point, Point)) -> f64 {
+    let i0 = project(q.0, p);
+    let i1 = project(q.1, p);
+    let length = distance(i0, i1);
+    if length == 0.0 {
+        return 0.0;
+    }
+    (1.0 - 2.0 * distance(midpoint(p.0, p.1), midpoint(i0, i1)) / length).max(0.0)
+}
+
+/// The projection of a point onto the line through a link
+fn project(point : Point, (s, e) : (Point, Point)) -> Point {
+    let v = vector((s, e));
+    let t = dot((point.0 - s.0, point.1 - s.1), v) / dot(v, v);
+    (s.0 + t * v.0, s.1 + t * v.1)
+}
+
+fn vector((s, e) : (Point, Point)) -> Point {
+    (e.0 - s.0, e.1 - s.1)
+}
+
+fn dot(a : Point, b : Point) -> f64 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+fn distance(a : Point, b : Point) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+fn midpoint(a : Point, b : Point) -> Point {
+    ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bundling::*;
+
+    #[test]
+    fn test_subdivide() {
+        let points = subdivide((0.0, 0.0), (8.0, 0.0), &[(4.0, 0.0)]);
+        assert_eq!(points, vec![(2.0, 0.0), (4.0, 0.0), (6.0, 0.0)]);
+        let points = subdivide((0.0, 0.0), (0.0, 0.0), &[(0.0, 0.0)]);
+        assert_eq!(points.len(), 3);
+    }
+
+    #[test]
+    fn test_compatibility() {
+        let p = ((0.0, 0.0), (100.0, 0.0));
+        assert!((compatibility(p, ((0.0, 10.0), (100.0, 10.0))) - 100.0 / 110.0).abs() < 1e-9);
+        assert_eq!(compatibility(p, ((50.0, -50.0), (50.0, 50.0))), 0.0);
+        assert_eq!(compatibility(p, ((300.0, 0.0), (400.0, 0.0))), 0.0);
+        assert_eq!(compatibility(p, ((0.0, 0.0), (0.0, 0.0))), 0.0);
+    }
+
+    #[test]
+    fn test_bundle() {
+        let links = [((0.0, 0.0), (100.0, 0.0)), ((0.0, 10.0), (100.0, 10.0)), ((100.0, 20.0), (0.0, 20.0))];
+        let straight = bundle(&links, 0.0);
+        assert_eq!(straight[0].len(), 31);
+        assert!(straight[0].iter().all(|p| p.1.abs() < 1e-9));
+        // The middles of the parallel links are drawn together, even for
+        // the link that runs the other way
+        let bundled = bundle(&links, 1.0);
+        for points in bundled.iter() {
+            assert!((points[15].1 - 10.0).abs() < 0.1);
+        }
+        let half = bundle(&links, 0.5);
+        assert!((half[0][15].1 - 5.0).abs() < 0.1);
+        assert!(serde_json::from_str::<crate::settings::Settings>(
+            r#"{"legend": [], "fixed_points": {}, "edge_bundling": 1.5}"#).is_err());
+    }
+}
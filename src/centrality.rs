@@ -0,0 +1,209 @@
+//! Centrality metrics of the vertices of the graph, which measure how
+//! important a dataset is to the structure of the cloud
+use crate::graph::Graph;
+use std::collections::{HashMap, VecDeque};
+
+/// The damping factor of PageRank
+const DAMPING : f64 = 0.85;
+/// The maximum number of iterations of PageRank
+const MAX_ITERATIONS : usize = 100;
+
+/// A centrality metric
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash,Deserialize,Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    /// The number of datasets linked to or from the dataset
+    Degree,
+    /// The fraction of shortest paths between other datasets that pass
+    /// through the dataset (ignoring the direction of links)
+    Betweenness,
+    /// The PageRank of the dataset following the direction of links
+    PageRank,
+    /// The largest k such that the dataset is in the k-core, the part of the
+    /// cloud in which every dataset is linked to or from at least k others
+    Core
+}
+
+impl Metric {
+    /// Calculate this metric for every vertex of the graph
+    pub fn compute(&self, graph : &Graph) -> Vec<f64> {
+        match self {
+            Metric::Degree => neighbours(graph).iter().map(|n| n.len() as f64).collect(),
+            Metric::Betweenness => betweenness(graph),
+            Metric::PageRank => pagerank(graph),
+            Metric::Core => core_numbers(graph).into_iter().map(|c| c as f64).collect()
+        }
+    }
+
+    /// Calculate this metric for every vertex of the graph, by the name of
+    /// the vertex
+    pub fn by_name(&self, graph : &Graph) -> HashMap<String, f64> {
+        let values = self.compute(graph);
+        graph.vertices().map(|(i, name)| (name.to_string(), values[i])).collect()
+    }
+}
+
+/// The distinct neighbours of each vertex, ignoring the direction of links
+fn neighbours(graph : &Graph) -> Vec<Vec<usize>> {
+    (0..graph.n).map(|i| {
+        let mut adjacent = graph.neighbours(i);
+        adjacent.retain(|&j| j != i);
+        adjacent
+    }).collect()
+}
+
+/// The betweenness centrality of each vertex by Brandes' algorithm,
+/// normalised so that the centre of a star has a betweenness of one
+pub fn betweenness(graph : &Graph) -> Vec<f64> {
+    let n = graph.n;
+    let adjacent = neighbours(graph);
+    let mut centrality = vec![0.0; n];
+    for s in 0..n {
+        let mut stack = Vec::new();
+        let mut predecessors = vec![Vec::new(); n];
+        let mut paths = vec![0.0; n];
+        let mut distance : Vec<Option<usize>> = vec![None; n];
+        paths[s] = 1.0;
+        distance[s] = Some(0);
+        let mut queue = VecDeque::new();
+        queue.push_back(s);
+        while let Some(v) = queue.pop_front() {
+            stack.push(v);
+            let d = distance[v].unwrap_or(0);
+            for &w in adjacent[v].iter() {
+                if distance[w].is_none() {
+                    distance[w] = Some(d + 1);
+                    queue.push_back(w);
+                }
+                if distance[w] == Some(d + 1) {
+                    paths[w] += paths[v];
+                    predecessors[w].push(v);
+                }
+            }
+        }
+        let mut dependency = vec![0.0; n];
+        while let Some(w) = stack.pop() {
+            for &v in predecessors[w].iter() {
+                dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+            }
+            if w != s {
+                centrality[w] += dependency[w];
+            }
+        }
+    }
+    // Each path is counted in both directions
+    let pairs = if n > 2 { ((n - 1) * (n - 2)) as f64 } else { 1.0 };
+    centrality.iter().map(|c| c / pairs).collect()
+}
+
+/// The PageRank of each vertex, where the ranks sum to one
+pub fn pagerank(graph : &Graph) -> Vec<f64> {
+    let n = graph.n;
+    if n == 0 {
+        return Vec::new();
+    }
+    let outgoing : Vec<Vec<usize>> = (0..n).map(|v| graph.out_edges(v).map(|e| e.trg).collect()).collect();
+    let mut rank = vec![1.0 / n as f64; n];
+    for _ in 0..MAX_ITERATIONS {
+        let dangling : f64 = (0..n).filter(|&v| outgoing[v].is_empty()).map(|v| rank[v]).sum();
+        let base = (1.0 - DAMPING + DAMPING * dangling) / n as f64;
+        let mut next = vec![base; n];
+        for v in 0..n {
+            for &w in outgoing[v].iter() {
+                next[w] += DAMPING * rank[v] / outgoing[v].len() as f64;
+            }
+        }
+        let change : f64 = next.iter().zip(rank.iter()).map(|(a, b)| (a - b).abs()).sum();
+        rank = next;
+        if change < 1e-10 {
+            break;
+        }
+    }
+    rank
+}
+
+/// The core number of each vertex, ignoring the direction of links
+pub fn core_numbers(graph : &Graph) -> Vec<usize> {
+    let adjacent = neighbours(graph);
+    let mut degree : Vec<usize> = adjacent.iter().map(|a| a.len()).collect();
+    let mut core = vec![0; graph.n];
+    let mut removed = vec![false; graph.n];
+    let mut remaining = graph.n;
+    let mut k = 0;
+    while remaining > 0 {
+        let mut stack : Vec<usize> = (0..graph.n)
+            .filter(|&v| !removed[v] && degree[v] <= k).collect();
+        if stack.is_empty() {
+            k += 1;
+            continue;
+        }
+        while let Some(v) = stack.pop() {
+            if removed[v] {
+                continue;
+            }
+            removed[v] = true;
+            core[v] = k;
+            remaining -= 1;
+            for &w in adjacent[v].iter() {
+                if !removed[w] {
+                    degree[w] -= 1;
+                    if degree[w] <= k {
+                        stack.push(w);
+                    }
+                }
+            }
+        }
+    }
+    core
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::centrality::*;
+    use crate::graph::Edge;
+
+    /// A triangle (0, 1, 2) with a tail 2 -> 3 -> 4
+    fn triangle_with_tail() -> Graph {
+        let mut g = Graph::new();
+        for i in 0..5 {
+            g.add_vertex(&i.to_string());
+        }
+        g.add_edge(Edge::new(0, 1));
+        g.add_edge(Edge::new(1, 2));
+        g.add_edge(Edge::new(2, 0));
+        g.add_edge(Edge::new(0, 2));
+        g.add_edge(Edge::new(2, 3));
+        g.add_edge(Edge::new(3, 4));
+        g
+    }
+
+    #[test]
+    fn test_degree_and_core() {
+        let g = triangle_with_tail();
+        assert_eq!(Metric::Degree.compute(&g), vec![2.0, 2.0, 3.0, 2.0, 1.0]);
+        assert_eq!(core_numbers(&g), vec![2, 2, 2, 1, 1]);
+        assert_eq!(Metric::Core.by_name(&g)["4"], 1.0);
+    }
+
+    #[test]
+    fn test_betweenness() {
+        let g = triangle_with_tail();
+        let b = betweenness(&g);
+        // 2 is on the paths from 0 and 1 to 3 and 4, out of six pairs
+        assert!((b[2] - 4.0 / 6.0).abs() < 1e-9);
+        assert!((b[3] - 3.0 / 6.0).abs() < 1e-9);
+        assert_eq!(b[0], 0.0);
+        assert_eq!(b[4], 0.0);
+    }
+
+    #[test]
+    fn test_pagerank() {
+        let g = triangle_with_tail();
+        let p = pagerank(&g);
+        assert!((p.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        // 4 collects the rank flowing down the tail
+        assert!(p[4] > p[1]);
+        assert!(p[2] > p[1]);
+        assert_eq!(pagerank(&Graph::new()), Vec::<f64>::new());
+    }
+}
@@ -0,0 +1,313 @@
+//! Checks the availability of the access points (downloads, examples and
+//! SPARQL endpoints) of the datasets, recording the results in the `status`
+//! and `media_type` of each access point
+use serde_json::{Map, Value};
+use std::fmt;
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use ureq::Agent;
+
+/// The accept header used when retrieving examples
+const EXAMPLE_ACCEPT : &str = "application/rdf+xml,text/turtle,application/n-triples,application/ld+json,*/*;q=0.9";
+
+/// The kinds of access point of a dataset
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum AccessKind {
+    FullDownload,
+    OtherDownload,
+    Example,
+    Sparql
+}
+
+impl AccessKind {
+    /// All kinds, in the order they are reported
+    pub const ALL : [AccessKind; 4] = [AccessKind::FullDownload,
+        AccessKind::OtherDownload, AccessKind::Example, AccessKind::Sparql];
+
+    /// The property of the dataset listing these access points
+    pub fn field(&self) -> &'static str {
+        match self {
+            AccessKind::FullDownload => "full_download",
+            AccessKind::OtherDownload => "other_download",
+            AccessKind::Example => "example",
+            AccessKind::Sparql => "sparql"
+        }
+    }
+
+    /// The property of the access point containing the URL
+    pub fn url_field(&self) -> &'static str {
+        match self {
+            AccessKind::FullDownload => "download_url",
+            _ => "access_url"
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            AccessKind::FullDownload => "Full Download",
+            AccessKind::OtherDownload => "Other Download",
+            AccessKind::Example => "Examples",
+            AccessKind::Sparql => "SPARQL"
+        }
+    }
+}
+
+/// The options for checking
+#[derive(Debug,Clone)]
+pub struct CheckOptions {
+    /// The time after which a request is considered failed
+    pub timeout : Duration,
+    /// The number of requests made in parallel
+    pub concurrency : usize
+}
+
+impl Default for CheckOptions {
+    fn default() -> CheckOptions {
+        CheckOptions {
+            timeout: Duration::from_secs(30),
+            concurrency: 16
+        }
+    }
+}
+
+/// The outcome of probing a single URL
+#[derive(Debug,Clone,PartialEq)]
+pub struct ProbeResult {
+    /// Either "OK" or "FAIL (reason)"
+    pub status : String,
+    /// The content type returned by the server
+    pub media_type : Option<String>
+}
+
+struct Probe {
+    dataset : String,
+    kind : AccessKind,
+    index : usize,
+    url : String
+}
+
+/// Check every access point in the data (a map from identifiers to the JSON
+/// of each dataset), updating their `status` and `media_type` properties
+pub fn check_data(data : &mut Map<String, Value>, options : &CheckOptions) {
+    let mut probes = Vec::new();
+    for (key, dataset) in data.iter() {
+        for kind in AccessKind::ALL.iter() {
+            for (index, entry) in access_points(dataset, *kind).iter().enumerate() {
+                if let Some(url) = entry.get(kind.url_field()).and_then(|u| u.as_str()) {
+                    probes.push(Probe {
+                        dataset: key.clone(),
+                        kind: *kind,
+                        index,
+                        url: url.to_string()
+                    });
+                }
+            }
+        }
+    }
+
+    let agent : Agent = Agent::config_builder()
+        .timeout_global(Some(options.timeout))
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let n_probes = probes.len();
+    let queue = Arc::new(Mutex::new(probes));
+    let (sender, receiver) = channel();
+    let mut workers = Vec::new();
+    for _ in 0..options.concurrency.max(1) {
+        let queue = queue.clone();
+        let sender = sender.clone();
+        let agent = agent.clone();
+        workers.push(thread::spawn(move || {
+            loop {
+                let next = queue.lock().expect("Check queue poisoned").pop();
+                match next {
+                    Some(probe) => {
+                        let result = probe_url(&agent, probe.kind, &probe.url);
+                        if sender.send((probe, result)).is_err() {
+                            break;
+                        }
+                    },
+                    None => break
+                }
+            }
+        }));
+    }
+    drop(sender);
+
+    for (i, (probe, result)) in receiver.iter().enumerate() {
+        eprintln!("[{}/{}] {} {}", i + 1, n_probes, probe.url, result.status);
+        if let Some(entry) = data.get_mut(&probe.dataset)
+            .and_then(|d| d.get_mut(probe.kind.field()))
+            .and_then(|l| l.get_mut(probe.index))
+            .and_then(|e| e.as_object_mut()) {
+            entry.insert("status".to_string(), Value::String(result.status));
+            if let Some(media_type) = result.media_type {
+                entry.insert("media_type".to_string(), Value::String(media_type));
+            }
+        }
+    }
+    for worker in workers {
+        worker.join().expect("Check thread panicked");
+    }
+}
+
+/// Probe a single URL. Downloads and SPARQL endpoints are checked with a
+/// HEAD request and examples are retrieved with a GET request
+pub fn probe_url(agent : &Agent, kind : AccessKind, url : &str) -> ProbeResult {
+    let response = if kind == AccessKind::Example {
+        agent.get(url).header("Accept", EXAMPLE_ACCEPT).call()
+    } else {
+        agent.head(url).call()
+    };
+    match response {
+        Ok(r) if r.status().as_u16() == 200 => ProbeResult {
+            status: "OK".to_string(),
+            media_type: if kind == AccessKind::Sparql {
+                None
+            } else {
+                r.headers().get("content-type")
+                    .and_then(|c| c.to_str().ok())
+                    .map(|c| c.to_string())
+            }
+        },
+        Ok(r) => ProbeResult {
+            status: format!("FAIL ({})", r.status().as_u16()),
+            media_type: None
+        },
+        Err(e) => ProbeResult {
+            status: format!("FAIL ({})", e),
+            media_type: None
+        }
+    }
+}
+
+fn access_points(dataset : &Value, kind : AccessKind) -> Vec<Value> {
+    dataset.get(kind.field())
+        .and_then(|l| l.as_array())
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// A summary of the availability of the datasets
+#[derive(Debug,Clone,PartialEq,Default)]
+pub struct Summary {
+    /// The number of datasets
+    pub resources : usize,
+    /// The number of datasets with at least one working access point
+    pub resources_available : usize,
+    /// The number of access points of each kind (in the order of `AccessKind::ALL`)
+    pub links : [usize; 4],
+    /// The number of working access points of each kind
+    pub links_available : [usize; 4]
+}
+
+/// Summarise the status of the access points in the data
+pub fn summarise(data : &Map<String, Value>) -> Summary {
+    let mut summary = Summary::default();
+    for dataset in data.values() {
+        summary.resources += 1;
+        let mut success = false;
+        for (i, kind) in AccessKind::ALL.iter().enumerate() {
+            for entry in access_points(dataset, *kind).iter() {
+                summary.links[i] += 1;
+                if entry.get("status").and_then(|s| s.as_str()) == Some("OK") {
+                    summary.links_available[i] += 1;
+                    success = true;
+                }
+            }
+        }
+        if success {
+            summary.resources_available += 1;
+        }
+    }
+    summary
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "|                | Status    |")?;
+        writeln!(f, "|----------------|-----------|")?;
+        writeln!(f, "| {:<14} | {:>4}/{:>4} |", "Resources",
+                 self.resources_available, self.resources)?;
+        for (i, kind) in AccessKind::ALL.iter().enumerate() {
+            writeln!(f, "| {:<14} | {:>4}/{:>4} |", kind.title(),
+                     self.links_available[i], self.links[i])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::check::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// A mock HTTP server that answers every request with the status code
+    /// given by the path, e.g., `/404`
+    fn mock_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let path = request_line.split(' ').nth(1).unwrap_or("/");
+                let code = path.trim_start_matches('/').parse::<u16>().unwrap_or(200);
+                write!(stream, "HTTP/1.1 {} X\r\nContent-Type: text/turtle\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", code).unwrap();
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn test_check_data() {
+        let server = mock_server();
+        let mut data : Map<String, Value> = serde_json::from_str(&format!(r#"{{
+            "a": {{
+                "full_download": [{{ "download_url": "{0}/200" }}],
+                "sparql": [{{ "access_url": "{0}/404" }}]
+            }},
+            "b": {{
+                "example": [{{ "access_url": "{0}/500", "media_type": "" }}],
+                "other_download": [{{ "title": "No URL" }}]
+            }}
+        }}"#, server)).unwrap();
+
+        check_data(&mut data, &CheckOptions {
+            timeout: Duration::from_secs(5),
+            concurrency: 2
+        });
+
+        assert_eq!(data["a"]["full_download"][0]["status"], "OK");
+        assert_eq!(data["a"]["full_download"][0]["media_type"], "text/turtle");
+        assert_eq!(data["a"]["sparql"][0]["status"], "FAIL (404)");
+        assert_eq!(data["b"]["example"][0]["status"], "FAIL (500)");
+        assert!(data["b"]["other_download"][0].get("status").is_none());
+
+        let summary = summarise(&data);
+        assert_eq!(summary.resources, 2);
+        assert_eq!(summary.resources_available, 1);
+        assert_eq!(summary.links, [1, 1, 1, 1]);
+        assert_eq!(summary.links_available, [1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_probe_unreachable() {
+        let agent : Agent = Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(1)))
+            .build()
+            .into();
+        let result = probe_url(&agent, AccessKind::Sparql, "http://127.0.0.1:1/sparql");
+        assert!(result.status.starts_with("FAIL ("));
+    }
+}
@@ -0,0 +1,246 @@
+//! Detection of communities (densely linked groups of datasets) in the graph,
+//! so that clouds without meaningful domains can be coloured and laid out by
+//! the structure of their links
+use crate::data::Dataset;
+use crate::graph::Graph;
+use crate::settings::LegendEntry;
+use std::collections::HashMap;
+
+/// The maximum number of passes over the vertices in each level of the
+/// Louvain method
+const MAX_PASSES : usize = 100;
+
+/// Find the communities of the graph with the Louvain method, treating each
+/// edge as an undirected link of weight one (or two for a bidirectional
+/// edge). The return value is the
+/// community of each vertex, where community 0 is the largest.
+pub fn louvain(graph : &Graph) -> Vec<usize> {
+    let mut adjacent = vec![Vec::new(); graph.n];
+    for edge in graph.edges().iter() {
+        let weight = if edge.is_bidirectional() { 2.0 } else { 1.0 };
+        adjacent[edge.src].push((edge.trg, weight));
+        adjacent[edge.trg].push((edge.src, weight));
+    }
+
+    let mut membership : Vec<usize> = (0..graph.n).collect();
+    loop {
+        let (communities, n, moved) = local_moving(&adjacent);
+        if !moved {
+            break;
+        }
+        for m in membership.iter_mut() {
+            *m = communities[*m];
+        }
+        adjacent = aggregate(&adjacent, &communities, n);
+    }
+    by_size(&membership)
+}
+
+/// Move each vertex to the neighbouring community that most increases the
+/// modularity, until no vertex moves. Returns the community of each vertex
+/// (numbered from zero), the number of communities and whether any vertex
+/// moved.
+fn local_moving(adjacent : &[Vec<(usize, f64)>]) -> (Vec<usize>, usize, bool) {
+    let n = adjacent.len();
+    let degree : Vec<f64> = adjacent.iter().map(|a| a.iter().map(|&(_, w)| w).sum()).collect();
+    let total : f64 = degree.iter().sum();
+    let mut community : Vec<usize> = (0..n).collect();
+    let mut community_degree = degree.clone();
+    let mut moved = false;
+
+    if total > 0.0 {
+        for _ in 0..MAX_PASSES {
+            let mut improved = false;
+            for i in 0..n {
+                let mut links : HashMap<usize, f64> = HashMap::new();
+                for &(j, w) in adjacent[i].iter() {
+                    if j != i {
+                        *links.entry(community[j]).or_insert(0.0) += w;
+                    }
+                }
+                let current = community[i];
+                community_degree[current] -= degree[i];
+                let gain = |c : usize| {
+                    links.get(&c).cloned().unwrap_or(0.0) - community_degree[c] * degree[i] / total
+                };
+                let mut best = current;
+                let mut best_gain = gain(current);
+                let mut candidates : Vec<usize> = links.keys().cloned().collect();
+                candidates.sort();
+                for c in candidates {
+                    let g = gain(c);
+                    if g > best_gain + 1e-12 {
+                        best = c;
+                        best_gain = g;
+                    }
+                }
+                community_degree[best] += degree[i];
+                if best != current {
+                    community[i] = best;
+                    improved = true;
+                    moved = true;
+                }
+            }
+            if !improved {
+                break;
+            }
+        }
+    }
+
+    let mut ids = HashMap::new();
+    for c in community.iter_mut() {
+        let next = ids.len();
+        *c = *ids.entry(*c).or_insert(next);
+    }
+    (community, ids.len(), moved)
+}
+
+/// Build the graph whose vertices are the communities
+fn aggregate(adjacent : &[Vec<(usize, f64)>], communities : &[usize], n : usize) -> Vec<Vec<(usize, f64)>> {
+    let mut weights : Vec<HashMap<usize, f64>> = vec![HashMap::new(); n];
+    for (i, a) in adjacent.iter().enumerate() {
+        for &(j, w) in a.iter() {
+            *weights[communities[i]].entry(communities[j]).or_insert(0.0) += w;
+        }
+    }
+    weights.into_iter().map(|w| {
+        let mut w : Vec<(usize, f64)> = w.into_iter().collect();
+        w.sort_by_key(|&(j, _)| j);
+        w
+    }).collect()
+}
+
+/// Renumber the communities so that the largest is 0, the next largest 1 and
+/// so on (ties are broken by the first vertex of the community)
+fn by_size(communities : &[usize]) -> Vec<usize> {
+    let mut sizes : HashMap<usize, (usize, usize)> = HashMap::new();
+    for (i, &c) in communities.iter().enumerate() {
+        sizes.entry(c).or_insert((0, i)).0 += 1;
+    }
+    let mut order : Vec<(usize, (usize, usize))> = sizes.into_iter().collect();
+    order.sort_by(|a, b| (b.1).0.cmp(&(a.1).0).then((a.1).1.cmp(&(b.1).1)));
+    let ids : HashMap<usize, usize> = order.iter().enumerate()
+        .map(|(i, &(c, _))| (c, i)).collect();
+    communities.iter().map(|c| ids[c]).collect()
+}
+
+/// The number of communities
+pub fn count(communities : &[usize]) -> usize {
+    communities.iter().max().map(|c| c + 1).unwrap_or(0)
+}
+
+/// The modularity of a division of the graph into communities, between -0.5
+/// (no links within communities) and 1 (no links between communities)
+pub fn modularity(graph : &Graph, communities : &[usize]) -> f64 {
+    let mut within = 0.0;
+    let mut degree = vec![0.0; count(communities)];
+    let mut total = 0.0;
+    for edge in graph.edges().iter() {
+        let weight = if edge.is_bidirectional() { 2.0 } else { 1.0 };
+        if communities[edge.src] == communities[edge.trg] {
+            within += 2.0 * weight;
+        }
+        degree[communities[edge.src]] += weight;
+        degree[communities[edge.trg]] += weight;
+        total += 2.0 * weight;
+    }
+    if total == 0.0 {
+        return 0.0;
+    }
+    within / total - degree.iter().map(|d| (d / total) * (d / total)).sum::<f64>()
+}
+
+/// The domain given to the datasets in a community
+pub fn domain_name(community : usize) -> String {
+    format!("community-{}", community + 1)
+}
+
+/// Set the domain of every dataset in the graph to its community
+pub fn assign_domains(data : &mut HashMap<String, Dataset>, graph : &Graph, communities : &[usize]) {
+    for (i, name) in graph.vertices() {
+        if let Some(dataset) = data.get_mut(name) {
+            dataset.domain = domain_name(communities[i]);
+        }
+    }
+}
+
+/// `n` distinct colours, spreading the hues by the golden angle
+pub fn palette(n : usize) -> Vec<String> {
+    (0..n).map(|i| {
+        let hue = (i as f64 * 137.508) % 360.0;
+        hsl_to_hex(hue, 0.65, 0.6)
+    }).collect()
+}
+
+fn hsl_to_hex(hue : f64, saturation : f64, lightness : f64) -> String {
+    let c = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = c * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let m = lightness - c / 2.0;
+    let (r, g, b) = match (hue / 60.0) as usize {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x)
+    };
+    let byte = |v : f64| ((v + m) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", byte(r), byte(g), byte(b))
+}
+
+/// A legend with an entry and an automatically chosen colour for each
+/// community
+pub fn legend(communities : &[usize]) -> Vec<LegendEntry> {
+    palette(count(communities)).into_iter().enumerate().map(|(i, colour)| {
+        LegendEntry {
+            title: format!("Community {}", i + 1),
+            domain: Some(domain_name(i)),
+            colour,
+            keywords: None
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::community::*;
+    use crate::graph::Edge;
+
+    /// Two cliques of four vertices joined by a single edge
+    fn two_cliques() -> Graph {
+        let mut g = Graph::new();
+        for i in 0..8 {
+            g.add_vertex(&format!("v{}", i));
+        }
+        for offset in [0, 4] {
+            for i in 0..4 {
+                for j in (i + 1)..4 {
+                    g.add_edge(Edge::new(offset + i, offset + j));
+                }
+            }
+        }
+        g.add_edge(Edge::new(3, 4));
+        g
+    }
+
+    #[test]
+    fn test_louvain() {
+        let g = two_cliques();
+        let c = louvain(&g);
+        assert_eq!(count(&c), 2);
+        assert_eq!(c, vec![0, 0, 0, 0, 1, 1, 1, 1]);
+        assert!(modularity(&g, &c) > 0.4);
+        assert!(modularity(&g, &[0; 8]).abs() < 1e-9);
+        assert_eq!(louvain(&Graph::new()), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_legend() {
+        let l = legend(&[0, 1, 2, 1]);
+        assert_eq!(l.len(), 3);
+        assert_eq!(l[1].domain, Some("community-2".to_string()));
+        assert_eq!(palette(1), vec!["#db5757".to_string()]);
+        assert_eq!(l[0].colour.len(), 7);
+        assert_ne!(l[0].colour, l[1].colour);
+    }
+}
@@ -14,7 +14,11 @@
 //!        "value": "456"
 //!     }],
 //!     "identifier": "dataset1",
-//!     "domain": "user-generated"
+//!     "domain": "user-generated",
+//!     "sparql": [{
+//!        "access_url": "http://example.com/sparql",
+//!        "status": "OK"
+//!     }]
 //!   },
 //!   "dataset2": {
 //!     "description": {
@@ -27,10 +31,9 @@
 //!   },
 use std::collections::HashMap;
 use serde::de::{Deserialize,Deserializer,Visitor};
-use serde;
 use std::fmt;
 
-#[derive(Debug,Deserialize,Clone)]
+#[derive(Debug,Deserialize,Clone,Default)]
 /// A dataset
 pub struct Dataset {
     pub description : HashMap<String, Option<String>>,
@@ -39,28 +42,409 @@ pub struct Dataset {
     pub identifier : String,
     pub domain : String,
     pub triples : IntLike,
-    pub keywords : Vec<String>
+    pub keywords : Vec<String>,
+    /// The homepage of the dataset
+    #[serde(default)]
+    pub website : Option<String>,
+    /// The DOI of the dataset
+    #[serde(default)]
+    pub doi : Option<String>,
+    /// Downloads of the full dataset
+    #[serde(default)]
+    pub full_download : Vec<AccessPoint>,
+    /// Other downloads, e.g., partial dumps or documentation
+    #[serde(default)]
+    pub other_download : Vec<AccessPoint>,
+    /// Example resources of the dataset
+    #[serde(default)]
+    pub example : Vec<AccessPoint>,
+    /// SPARQL endpoints
+    #[serde(default)]
+    pub sparql : Vec<AccessPoint>
+}
+
+impl Dataset {
+    /// The description of the dataset, in English if available
+    pub fn description_text(&self) -> Option<&str> {
+        let mut languages : Vec<&String> = self.description.keys().collect();
+        languages.sort_by_key(|l| (l.as_str() != "en", l.as_str()));
+        languages.into_iter()
+            .filter_map(|l| self.description[l].as_deref())
+            .find(|d| !d.trim().is_empty())
+    }
+
+    /// Iterate over all the access points of the dataset
+    pub fn access_points(&self) -> impl Iterator<Item=&AccessPoint> {
+        self.full_download.iter()
+            .chain(self.other_download.iter())
+            .chain(self.example.iter())
+            .chain(self.sparql.iter())
+    }
+
+    /// Whether the dataset can currently be accessed, according to the 
+    /// `status` and `mirror` of its access points
+    pub fn availability(&self) -> Availability {
+        let mut checked = false;
+        for access_point in self.access_points() {
+            if access_point.is_available() {
+                return Availability::Available;
+            }
+            checked = checked || access_point.status.is_some();
+        }
+        if checked {
+            Availability::Unavailable
+        } else {
+            Availability::Unknown
+        }
+    }
+}
+
+/// A download, example or endpoint of a dataset
+#[derive(Debug,Deserialize,Clone,Default)]
+pub struct AccessPoint {
+    /// The URL of a download
+    #[serde(default)]
+    pub download_url : Option<String>,
+    /// The URL of an endpoint, example or other download
+    #[serde(default)]
+    pub access_url : Option<String>,
+    /// The result of the last check, either "OK" or "FAIL (reason)"
+    pub status : Option<String>,
+    /// Any mirrors of this access point (e.g., `ipfs:` URIs)
+    pub mirror : Option<Vec<String>>
+}
+
+impl AccessPoint {
+    /// The URL of this access point
+    pub fn url(&self) -> Option<&str> {
+        self.download_url.as_deref().or(self.access_url.as_deref())
+    }
+
+    /// If the last check succeeded or there is a mirror of this access point
+    pub fn is_available(&self) -> bool {
+        self.status.as_deref() == Some("OK") ||
+            self.mirror.iter().any(|m| !m.is_empty())
+    }
+}
+
+/// The availability of a dataset
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Availability {
+    /// At least one access point is working
+    Available,
+    /// All access points have been checked and none are working
+    Unavailable,
+    /// No access point has been checked
+    Unknown
 }
 
 /// A link from a dataset to a target dataset
 #[derive(Debug,Deserialize,Clone)]
 pub struct Link {
     pub target : String,
-    pub value : String
+    pub value : IntLike
 }
 
-#[derive(Debug,Clone)]
-pub struct IntLike(Option<i64>);
+/// An integer value that may be given in the data either as a number or as a
+/// (possibly human-formatted) string such as `"1,234,567"` or `"1.2M"`
+#[derive(Debug,Clone,Default)]
+pub struct IntLike {
+    value : Option<i64>,
+    unparsed : Option<String>
+}
 
 impl From<i64> for IntLike {
     fn from(x : i64) -> Self {
-        IntLike(Some(x))
+        IntLike { value: Some(x), unparsed: None }
     }
 }
 
 impl IntLike {
+    /// The value, or zero if the value was missing or could not be parsed
     pub fn get(&self) -> i64 {
-        self.0.unwrap_or(0).clone()
+        self.value.unwrap_or(0)
+    }
+
+    /// The original text of the value if it could not be understood
+    pub fn unparsed(&self) -> Option<&str> {
+        self.unparsed.as_deref()
+    }
+
+    fn from_str(text : &str) -> IntLike {
+        if text.trim().is_empty() {
+            IntLike::default()
+        } else {
+            match parse_int_like(text) {
+                Some(v) => IntLike::from(v),
+                None => IntLike { value: None, unparsed: Some(text.to_string()) }
+            }
+        }
+    }
+
+    fn from_f64(x : f64) -> IntLike {
+        if x.is_finite() && x.abs() < i64::MAX as f64 {
+            IntLike::from(x.round() as i64)
+        } else {
+            IntLike { value: None, unparsed: Some(x.to_string()) }
+        }
+    }
+}
+
+/// A value in the data that could not be parsed as a number
+#[derive(Debug,Clone,PartialEq)]
+pub struct Unparseable {
+    /// The identifier of the dataset containing the value
+    pub dataset : String,
+    /// The field, either `triples` or `links/<target>`
+    pub field : String,
+    /// The text as given in the data
+    pub text : String
+}
+
+/// List all triple counts and link values that could not be parsed (and so
+/// are treated as zero)
+pub fn unparseable_values(data : &HashMap<String, Dataset>) -> Vec<Unparseable> {
+    let mut result = Vec::new();
+    for (key, dataset) in data.iter() {
+        if let Some(text) = dataset.triples.unparsed() {
+            result.push(Unparseable {
+                dataset: key.clone(),
+                field: "triples".to_string(),
+                text: text.to_string()
+            });
+        }
+        for link in dataset.links.iter() {
+            if let Some(text) = link.value.unparsed() {
+                result.push(Unparseable {
+                    dataset: key.clone(),
+                    field: format!("links/{}", link.target),
+                    text: text.to_string()
+                });
+            }
+        }
+    }
+    result.sort_by(|a, b| (&a.dataset, &a.field).cmp(&(&b.dataset, &b.field)));
+    result
+}
+
+/// How to resolve a field that has different values in two data files
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum MergeStrategy {
+    /// Keep the value from the file loaded first
+    First,
+    /// Take the value from the file loaded last
+    Last
+}
+
+/// A dataset that occurs in more than one of the merged data files
+#[derive(Debug,Clone,PartialEq)]
+pub struct Overlap {
+    /// The key of the dataset
+    pub dataset : String,
+    /// The names of the fields that had conflicting values
+    pub conflicts : Vec<String>,
+    /// The number of links that were only in the new data
+    pub new_links : usize
+}
+
+impl fmt::Display for Overlap {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} new link(s)", self.dataset, self.new_links)?;
+        if !self.conflicts.is_empty() {
+            write!(f, ", conflicting {}", self.conflicts.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Merge the datasets of `other` into `data`. Datasets occurring in both have
+/// their links, keywords and access points unioned, while fields that are set in both with
+/// different values are resolved according to `strategy`. Returns a report of
+/// all datasets that occurred in both.
+pub fn merge(data : &mut HashMap<String, Dataset>, other : HashMap<String, Dataset>,
+             strategy : MergeStrategy) -> Vec<Overlap> {
+    let mut overlaps = Vec::new();
+    for (key, new) in other.into_iter() {
+        match data.get_mut(&key) {
+            Some(old) => overlaps.push(merge_dataset(&key, old, new, strategy)),
+            None => { data.insert(key, new); }
+        }
+    }
+    overlaps.sort_by(|a, b| a.dataset.cmp(&b.dataset));
+    overlaps
+}
+
+/// Merge an optional field, noting a conflict if both values are set and differ
+fn merge_field<T : PartialEq>(name : &str, old : &mut Option<T>, new : Option<T>,
+                              take_new : bool, conflicts : &mut Vec<String>) {
+    match (old.as_ref(), new) {
+        (None, v) => *old = v,
+        (Some(v1), Some(v2)) if *v1 != v2 => {
+            conflicts.push(name.to_string());
+            if take_new {
+                *old = Some(v2);
+            }
+        },
+        _ => {}
+    }
+}
+
+/// Merge access points by their URL, noting a conflict if the status or
+/// mirrors of an access point in both differ
+fn merge_access_points(name : &str, old : &mut Vec<AccessPoint>, new : Vec<AccessPoint>,
+                       take_new : bool, conflicts : &mut Vec<String>) {
+    for point in new.into_iter() {
+        match old.iter_mut().find(|p| p.url().is_some() && p.url() == point.url()) {
+            Some(p) => {
+                let field = format!("{}/{}", name, point.url().unwrap_or_default());
+                merge_field(&format!("{}/status", field), &mut p.status, point.status,
+                            take_new, conflicts);
+                merge_field(&format!("{}/mirror", field), &mut p.mirror, point.mirror,
+                            take_new, conflicts);
+            },
+            None => old.push(point)
+        }
+    }
+}
+
+fn merge_dataset(key : &str, old : &mut Dataset, new : Dataset, 
+                 strategy : MergeStrategy) -> Overlap {
+    let mut conflicts = Vec::new();
+    let take_new = strategy == MergeStrategy::Last;
+
+    merge_field("title", &mut old.title, new.title, take_new, &mut conflicts);
+    merge_field("website", &mut old.website, new.website, take_new, &mut conflicts);
+    merge_field("doi", &mut old.doi, new.doi, take_new, &mut conflicts);
+
+    if old.identifier != new.identifier {
+        conflicts.push("identifier".to_string());
+        if take_new {
+            old.identifier = new.identifier;
+        }
+    }
+
+    if old.domain.is_empty() {
+        old.domain = new.domain;
+    } else if !new.domain.is_empty() && old.domain != new.domain {
+        conflicts.push("domain".to_string());
+        if take_new {
+            old.domain = new.domain;
+        }
+    }
+
+    if old.triples.value.is_none() {
+        old.triples = new.triples;
+    } else if new.triples.value.is_some() && old.triples.get() != new.triples.get() {
+        conflicts.push("triples".to_string());
+        if take_new {
+            old.triples = new.triples;
+        }
+    }
+
+    for (lang, text) in new.description.into_iter() {
+        match old.description.get(&lang) {
+            Some(Some(t)) if text.is_some() && Some(t) != text.as_ref() => {
+                conflicts.push(format!("description@{}", lang));
+                if take_new {
+                    old.description.insert(lang, text);
+                }
+            },
+            Some(Some(_)) => {},
+            _ => { old.description.insert(lang, text); }
+        }
+    }
+
+    for keyword in new.keywords.into_iter() {
+        if !old.keywords.contains(&keyword) {
+            old.keywords.push(keyword);
+        }
+    }
+
+    merge_access_points("full_download", &mut old.full_download, new.full_download, take_new, &mut conflicts);
+    merge_access_points("other_download", &mut old.other_download, new.other_download, take_new, &mut conflicts);
+    merge_access_points("example", &mut old.example, new.example, take_new, &mut conflicts);
+    merge_access_points("sparql", &mut old.sparql, new.sparql, take_new, &mut conflicts);
+
+    let mut new_links = 0;
+    for link in new.links.into_iter() {
+        match old.links.iter_mut().find(|l| l.target == link.target) {
+            Some(l) => {
+                if l.value.get() != link.value.get() {
+                    conflicts.push(format!("links/{}", link.target));
+                    if take_new {
+                        l.value = link.value;
+                    }
+                }
+            },
+            None => {
+                old.links.push(link);
+                new_links += 1;
+            }
+        }
+    }
+
+    Overlap {
+        dataset: key.to_string(),
+        conflicts,
+        new_links
+    }
+}
+
+/// Parse a human-formatted count. This accepts thousands separators
+/// (`1,234,567`, `1.234.567`, `1 234 567`), decimals and scientific notation
+/// (`1.5e9`) and suffixes such as `1.2M`, `3 billion` or `500k`.
+fn parse_int_like(text : &str) -> Option<i64> {
+    let mut s = text.trim().to_lowercase();
+    for prefix in ["~", "≈", ">=", "<=", ">", "<", "+", "approx.", "approx", "about", "ca."] {
+        if let Some(rest) = s.strip_prefix(prefix) {
+            s = rest.trim_start().to_string();
+        }
+    }
+    for suffix in ["triples", "links", "+"] {
+        if let Some(rest) = s.strip_suffix(suffix) {
+            s = rest.trim_end().to_string();
+        }
+    }
+
+    let mut multiplier = 1.0;
+    for &(suffix, m) in [("thousand", 1e3), ("million", 1e6), ("billion", 1e9),
+                         ("trillion", 1e12), ("bn", 1e9), ("mm", 1e6), 
+                         ("k", 1e3), ("m", 1e6), ("b", 1e9), ("g", 1e9),
+                         ("t", 1e12)].iter() {
+        if let Some(rest) = s.strip_suffix(suffix) {
+            if rest.ends_with(|c : char| c.is_ascii_digit() || c.is_whitespace()) {
+                s = rest.trim_end().to_string();
+                multiplier = m;
+                break;
+            }
+        }
+    }
+
+    let s : String = s.chars()
+        .filter(|c| !matches!(c, ',' | '_' | '\'' | ' ' | '\u{a0}' | '\u{202f}'))
+        .collect();
+    if s.is_empty() || !s.starts_with(|c : char| c.is_ascii_digit() || c == '.' || c == '-') {
+        return None;
+    }
+
+    if let Ok(v) = s.parse::<i64>() {
+        return Some(v.saturating_mul(multiplier as i64));
+    }
+
+    // A dot followed by groups of three digits is a thousands separator
+    // unless a suffix indicates that a decimal is meant (e.g., "1.5M")
+    let groups : Vec<&str> = s.split('.').collect();
+    if multiplier == 1.0 && groups.len() > 1 && !groups[0].is_empty() &&
+        groups[0].len() <= 3 && groups[1..].iter().all(|g| {
+            g.len() == 3 && g.chars().all(|c| c.is_ascii_digit())
+        }) {
+        return groups.concat().parse::<i64>().ok();
+    }
+
+    match s.parse::<f64>() {
+        Ok(v) if (v * multiplier).is_finite() && (v * multiplier).abs() < i64::MAX as f64 => 
+            Some((v * multiplier).round() as i64),
+        _ => None
     }
 }
 
@@ -78,63 +462,233 @@ impl<'de> Visitor<'de> for IntLikeVisitor {
     type Value = IntLike;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("an integer between -2^31 and 2^31")
+        formatter.write_str("an integer or a string containing a number")
     }
 
     fn visit_i8<E>(self, value: i8) -> Result<IntLike, E>
         where E: serde::de::Error
     {
-        Ok(IntLike(Some(value as i64)))
+        Ok(IntLike::from(value as i64))
+    }
+
+    fn visit_i16<E>(self, value: i16) -> Result<IntLike, E>
+        where E: serde::de::Error
+    {
+        Ok(IntLike::from(value as i64))
     }
 
     fn visit_i32<E>(self, value: i32) -> Result<IntLike, E>
         where E: serde::de::Error
     {
-        Ok(IntLike(Some(value as i64)))
+        Ok(IntLike::from(value as i64))
     }
 
     fn visit_i64<E>(self, value: i64) -> Result<IntLike, E>
         where E: serde::de::Error
     {
-        Ok(IntLike(Some(value)))
+        Ok(IntLike::from(value))
     }
 
     fn visit_u8<E>(self, value: u8) -> Result<IntLike, E>
         where E: serde::de::Error
     {
-        Ok(IntLike(Some(value as i64)))
+        Ok(IntLike::from(value as i64))
+    }
+
+    fn visit_u16<E>(self, value: u16) -> Result<IntLike, E>
+        where E: serde::de::Error
+    {
+        Ok(IntLike::from(value as i64))
     }
 
     fn visit_u32<E>(self, value: u32) -> Result<IntLike, E>
         where E: serde::de::Error
     {
-        Ok(IntLike(Some(value as i64)))
+        Ok(IntLike::from(value as i64))
     }
 
     fn visit_u64<E>(self, value: u64) -> Result<IntLike, E>
         where E: serde::de::Error
     {
-        Ok(IntLike(Some(value as i64)))
+        Ok(IntLike::from(i64::try_from(value).unwrap_or(i64::MAX)))
+    }
+
+    fn visit_f32<E>(self, value: f32) -> Result<IntLike, E>
+        where E: serde::de::Error
+    {
+        Ok(IntLike::from_f64(value as f64))
     }
 
+    fn visit_f64<E>(self, value: f64) -> Result<IntLike, E>
+        where E: serde::de::Error
+    {
+        Ok(IntLike::from_f64(value))
+    }
 
     fn visit_str<E>(self, value :&str) -> Result<IntLike, E>
         where E: serde::de::Error
     {
-        Ok(IntLike(value.parse::<i64>().ok()))
+        Ok(IntLike::from_str(value))
     }
 
     fn visit_string<E>(self, value : String) -> Result<IntLike, E>
         where E: serde::de::Error
     {
-        Ok(IntLike(value.parse::<i64>().ok()))
+        Ok(IntLike::from_str(&value))
+    }
+
+    fn visit_bool<E>(self, value : bool) -> Result<IntLike, E>
+        where E: serde::de::Error
+    {
+        Ok(IntLike { value: None, unparsed: Some(value.to_string()) })
+    }
+
+    fn visit_unit<E>(self) -> Result<IntLike, E>
+        where E: serde::de::Error
+    {
+        Ok(IntLike::default())
     }
 
+    fn visit_none<E>(self) -> Result<IntLike, E>
+        where E: serde::de::Error
+    {
+        Ok(IntLike::default())
+    }
+}
 
-    // Similar for other methods:
-    //   - visit_i16
-    //   - visit_u8
-    //   - visit_u16
-    //   - visit_u32
-    //   - visit_u64
+/// A dataset with an identifier, domain, number of triples and links to
+/// targets (each of value 1) for the tests
+#[cfg(test)]
+pub fn make_dataset(s : &str, d : &str, triples : i64, links : &[&str]) -> Dataset {
+    Dataset {
+        identifier: s.to_string(),
+        domain: d.to_string(),
+        triples: IntLike::from(triples),
+        links: links.iter().map(|t| Link {
+            target: t.to_string(),
+            value: IntLike::from(1)
+        }).collect(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::data::*;
+
+    #[test]
+    fn test_parse_int_like() {
+        assert_eq!(parse_int_like("1234"), Some(1234));
+        assert_eq!(parse_int_like(" 1234 "), Some(1234));
+        assert_eq!(parse_int_like("1,234,567"), Some(1234567));
+        assert_eq!(parse_int_like("1.234.567"), Some(1234567));
+        assert_eq!(parse_int_like("1 234 567"), Some(1234567));
+        assert_eq!(parse_int_like("1.2M"), Some(1200000));
+        assert_eq!(parse_int_like("1.5 billion"), Some(1500000000));
+        assert_eq!(parse_int_like("500k"), Some(500000));
+        assert_eq!(parse_int_like("~3bn triples"), Some(3000000000));
+        assert_eq!(parse_int_like("1.5e9"), Some(1500000000));
+        assert_eq!(parse_int_like("2E+03"), Some(2000));
+        assert_eq!(parse_int_like("12.7"), Some(13));
+        assert_eq!(parse_int_like("unknown"), None);
+        assert_eq!(parse_int_like("many"), None);
+        assert_eq!(parse_int_like("m"), None);
+    }
+
+    #[test]
+    fn test_deserialize_int_like() {
+        let v : Vec<IntLike> = serde_json::from_str(
+            "[12, 12.6, -3, \"1,000\", \"lots\", null, \"\"]").unwrap();
+        assert_eq!(v.iter().map(|x| x.get()).collect::<Vec<i64>>(),
+            vec![12, 13, -3, 1000, 0, 0, 0]);
+        assert_eq!(v[4].unparsed(), Some("lots"));
+        assert_eq!(v[5].unparsed(), None);
+        assert_eq!(v[6].unparsed(), None);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut data = HashMap::new();
+        data.insert("a".to_string(), make_dataset("a", "geography", 10, &["b"]));
+        data.insert("b".to_string(), make_dataset("b", "", 10, &[]));
+        let mut other = HashMap::new();
+        other.insert("a".to_string(), make_dataset("a", "media", 10, &["b", "c"]));
+        other.insert("b".to_string(), make_dataset("b", "media", 10, &[]));
+        other.insert("c".to_string(), make_dataset("c", "media", 5, &["a"]));
+
+        let overlaps = merge(&mut data, other.clone(), MergeStrategy::First);
+        assert_eq!(data.len(), 3);
+        assert_eq!(data["a"].domain, "geography");
+        assert_eq!(data["a"].links.len(), 2);
+        assert_eq!(data["b"].domain, "media");
+        assert_eq!(overlaps.len(), 2);
+        assert_eq!(overlaps[0].conflicts, vec!["domain".to_string()]);
+        assert_eq!(overlaps[0].new_links, 1);
+        assert!(overlaps[1].conflicts.is_empty());
+
+        let overlaps = merge(&mut data, other, MergeStrategy::Last);
+        assert_eq!(data["a"].domain, "media");
+        assert_eq!(overlaps.len(), 3);
+        assert_eq!(overlaps[0].new_links, 0);
+    }
+
+    #[test]
+    fn test_merge_access_points() {
+        let read = |json : &str| -> HashMap<String, Dataset> { serde_json::from_str(json).unwrap() };
+        let first = r#"{ "a": { "description": {}, "title": "A", "links": [], "identifier": "a",
+            "domain": "", "triples": 0, "keywords": [],
+            "sparql": [{ "access_url": "http://a/sparql", "status": "FAIL (404)" }] } }"#;
+        let second = r#"{ "a": { "description": {}, "title": "A", "links": [], "identifier": "a",
+            "domain": "", "triples": 0, "keywords": [],
+            "sparql": [{ "access_url": "http://a/sparql", "status": "OK", "mirror": ["ipfs:Qm"] }],
+            "full_download": [{ "download_url": "http://a/dump.nt", "status": "OK" }] } }"#;
+
+        let mut data = read(first);
+        let overlaps = merge(&mut data, read(second), MergeStrategy::First);
+        assert_eq!(overlaps[0].conflicts, vec!["sparql/http://a/sparql/status".to_string()]);
+        assert_eq!(data["a"].sparql.len(), 1);
+        assert_eq!(data["a"].sparql[0].status.as_deref(), Some("FAIL (404)"));
+        assert_eq!(data["a"].sparql[0].mirror, Some(vec!["ipfs:Qm".to_string()]));
+        assert_eq!(data["a"].full_download[0].url(), Some("http://a/dump.nt"));
+
+        let mut data = read(first);
+        merge(&mut data, read(second), MergeStrategy::Last);
+        assert_eq!(data["a"].sparql[0].status.as_deref(), Some("OK"));
+        assert_eq!(data["a"].availability(), Availability::Available);
+    }
+
+    #[test]
+    fn test_availability() {
+        let data : HashMap<String, Dataset> = serde_json::from_str(r#"{
+            "a": { "description": {}, "title": "A", "links": [], "identifier": "a",
+                   "domain": "", "triples": 0, "keywords": [],
+                   "sparql": [{ "access_url": "http://a", "status": "FAIL (404)" }],
+                   "full_download": [{ "download_url": "http://a.nt", "status": "OK" }] },
+            "b": { "description": {}, "title": "B", "links": [], "identifier": "b",
+                   "domain": "", "triples": 0, "keywords": [],
+                   "other_download": [{ "access_url": "http://b", "status": "FAIL (404)" }] },
+            "c": { "description": {}, "title": "C", "links": [], "identifier": "c",
+                   "domain": "", "triples": 0, "keywords": [] },
+            "d": { "description": {}, "title": "D", "links": [], "identifier": "d",
+                   "domain": "", "triples": 0, "keywords": [],
+                   "full_download": [{ "download_url": "http://d.nt", "status": "FAIL (500)",
+                                       "mirror": ["ipfs:Qm"] }] }
+        }"#).unwrap();
+        assert_eq!(data["a"].availability(), Availability::Available);
+        assert_eq!(data["b"].availability(), Availability::Unavailable);
+        assert_eq!(data["c"].availability(), Availability::Unknown);
+        assert_eq!(data["d"].availability(), Availability::Available);
+    }
+
+    #[test]
+    fn test_description_text() {
+        let mut d = make_dataset("a", "media", 0, &[]);
+        assert_eq!(d.description_text(), None);
+        d.description.insert("en".to_string(), Some(" ".to_string()));
+        d.description.insert("de".to_string(), Some("Ein Datensatz".to_string()));
+        assert_eq!(d.description_text(), Some("Ein Datensatz"));
+        d.description.insert("en".to_string(), Some("A dataset".to_string()));
+        assert_eq!(d.description_text(), Some("A dataset"));
+    }
 }
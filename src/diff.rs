@@ -0,0 +1,227 @@
+//! The differences between two releases (snapshots) of the LOD cloud data
+use crate::data::{self, Dataset, MergeStrategy};
+use crate::scene::Highlights;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// The changes from an old to a new version of the data
+#[derive(Debug,Clone,PartialEq,Default,Serialize)]
+pub struct SnapshotDiff {
+    /// Datasets only in the new data
+    pub added : Vec<String>,
+    /// Datasets only in the old data
+    pub removed : Vec<String>,
+    /// Datasets whose domain changed
+    pub domain_changes : Vec<Change<String>>,
+    /// Datasets whose number of triples changed
+    pub triples_changes : Vec<Change<i64>>,
+    /// Links (source, target) only in the new data
+    pub links_added : Vec<(String, String)>,
+    /// Links (source, target) only in the old data
+    pub links_removed : Vec<(String, String)>,
+    /// Links in both whose value (number of links) changed
+    pub links_changed : Vec<LinkChange>
+}
+
+/// A change to a single value of a dataset
+#[derive(Debug,Clone,PartialEq,Serialize)]
+pub struct Change<A> {
+    pub dataset : String,
+    pub old : A,
+    pub new : A
+}
+
+/// A change to the value (number of links) of a link
+#[derive(Debug,Clone,PartialEq,Serialize)]
+pub struct LinkChange {
+    pub source : String,
+    pub target : String,
+    pub old : i64,
+    pub new : i64
+}
+
+/// Compare two versions of the data
+pub fn diff(old : &HashMap<String, Dataset>, new : &HashMap<String, Dataset>) -> SnapshotDiff {
+    let mut result = SnapshotDiff::default();
+
+    for (key, new_dataset) in new.iter() {
+        match old.get(key) {
+            None => result.added.push(key.clone()),
+            Some(old_dataset) => {
+                if old_dataset.domain != new_dataset.domain {
+                    result.domain_changes.push(Change {
+                        dataset: key.clone(),
+                        old: old_dataset.domain.clone(),
+                        new: new_dataset.domain.clone()
+                    });
+                }
+                if old_dataset.triples.get() != new_dataset.triples.get() {
+                    result.triples_changes.push(Change {
+                        dataset: key.clone(),
+                        old: old_dataset.triples.get(),
+                        new: new_dataset.triples.get()
+                    });
+                }
+            }
+        }
+    }
+    for key in old.keys() {
+        if !new.contains_key(key) {
+            result.removed.push(key.clone());
+        }
+    }
+
+    let old_links = links(old);
+    let new_links = links(new);
+    for (link, &new_value) in new_links.iter() {
+        match old_links.get(link) {
+            None => result.links_added.push(link.clone()),
+            Some(&old_value) if old_value != new_value => {
+                result.links_changed.push(LinkChange {
+                    source: link.0.clone(),
+                    target: link.1.clone(),
+                    old: old_value,
+                    new: new_value
+                });
+            },
+            Some(_) => {}
+        }
+    }
+    result.links_removed = old_links.keys()
+        .filter(|link| !new_links.contains_key(link)).cloned().collect();
+
+    result.added.sort();
+    result.removed.sort();
+    result.domain_changes.sort_by(|a, b| a.dataset.cmp(&b.dataset));
+    result.triples_changes.sort_by(|a, b| a.dataset.cmp(&b.dataset));
+    result
+}
+
+/// The value of each link (source, target) of the data
+fn links(data : &HashMap<String, Dataset>) -> BTreeMap<(String, String), i64> {
+    data.iter().flat_map(|(key, dataset)| {
+        dataset.links.iter().map(move |l| ((key.clone(), l.target.clone()), l.value.get()))
+    }).collect()
+}
+
+/// Combine both versions of the data, so that a single cloud showing both
+/// the added and removed datasets and links can be drawn. Values in the new
+/// data take precedence.
+pub fn union(old : &HashMap<String, Dataset>, new : &HashMap<String, Dataset>) -> HashMap<String, Dataset> {
+    let mut data = old.clone();
+    data::merge(&mut data, new.clone(), MergeStrategy::Last);
+    data
+}
+
+impl SnapshotDiff {
+    /// The highlighting of the changes for drawing the union of both versions
+    pub fn highlights(&self) -> Highlights {
+        let mut highlights = Highlights::default();
+        for change in self.domain_changes.iter() {
+            highlights.bubbles.insert(change.dataset.clone(), "changed".to_string());
+        }
+        for change in self.triples_changes.iter() {
+            highlights.bubbles.insert(change.dataset.clone(), "changed".to_string());
+        }
+        for dataset in self.added.iter() {
+            highlights.bubbles.insert(dataset.clone(), "added".to_string());
+        }
+        for dataset in self.removed.iter() {
+            highlights.bubbles.insert(dataset.clone(), "removed".to_string());
+        }
+        for link in self.links_added.iter() {
+            highlights.links.insert(link.clone(), "link-added".to_string());
+        }
+        for link in self.links_removed.iter() {
+            highlights.links.insert(link.clone(), "link-removed".to_string());
+        }
+        for change in self.links_changed.iter() {
+            highlights.links.insert((change.source.clone(), change.target.clone()),
+                                    "link-changed".to_string());
+        }
+        highlights
+    }
+}
+
+impl fmt::Display for SnapshotDiff {
+    /// Write the differences as a Markdown changelog
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# LOD Cloud Changes")?;
+        write_section(f, "Datasets added", self.added.to_vec())?;
+        write_section(f, "Datasets removed", self.removed.to_vec())?;
+        write_section(f, "Domain changes", self.domain_changes.iter().map(|c| {
+            format!("{}: {} → {}", c.dataset, c.old, c.new)
+        }).collect())?;
+        write_section(f, "Triples changes", self.triples_changes.iter().map(|c| {
+            format!("{}: {} → {}", c.dataset, c.old, c.new)
+        }).collect())?;
+        write_section(f, "Links added", self.links_added.iter().map(|(s, t)| {
+            format!("{} → {}", s, t)
+        }).collect())?;
+        write_section(f, "Links removed", self.links_removed.iter().map(|(s, t)| {
+            format!("{} → {}", s, t)
+        }).collect())?;
+        write_section(f, "Links changed", self.links_changed.iter().map(|c| {
+            format!("{} → {}: {} → {}", c.source, c.target, c.old, c.new)
+        }).collect())
+    }
+}
+
+fn write_section(f : &mut fmt::Formatter, title : &str, items : Vec<String>) -> fmt::Result {
+    writeln!(f)?;
+    writeln!(f, "## {} ({})", title, items.len())?;
+    if !items.is_empty() {
+        writeln!(f)?;
+        for item in items.iter() {
+            writeln!(f, "* {}", item)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::make_dataset;
+    use crate::diff::*;
+
+    #[test]
+    fn test_diff() {
+        let mut old = HashMap::new();
+        old.insert("a".to_string(), make_dataset("a", "media", 10, &["b", "c"]));
+        old.insert("b".to_string(), make_dataset("b", "media", 10, &[]));
+        old.insert("c".to_string(), make_dataset("c", "media", 10, &[]));
+        let mut new = HashMap::new();
+        new.insert("a".to_string(), make_dataset("a", "geography", 10, &["b", "d"]));
+        new.get_mut("a").unwrap().links[0].value = 5.into();
+        new.insert("b".to_string(), make_dataset("b", "media", 20, &[]));
+        new.insert("d".to_string(), make_dataset("d", "media", 10, &[]));
+
+        let d = diff(&old, &new);
+        assert_eq!(d.added, vec!["d".to_string()]);
+        assert_eq!(d.removed, vec!["c".to_string()]);
+        assert_eq!(d.domain_changes.len(), 1);
+        assert_eq!(d.domain_changes[0].new, "geography");
+        assert_eq!(d.triples_changes.len(), 1);
+        assert_eq!(d.triples_changes[0].dataset, "b");
+        assert_eq!(d.links_added, vec![("a".to_string(), "d".to_string())]);
+        assert_eq!(d.links_removed, vec![("a".to_string(), "c".to_string())]);
+        assert_eq!(d.links_changed, vec![LinkChange {
+            source: "a".to_string(),
+            target: "b".to_string(),
+            old: 1,
+            new: 5
+        }]);
+        assert!(d.to_string().contains("## Links changed (1)\n\n* a → b: 1 → 5\n"));
+
+        let u = union(&old, &new);
+        assert_eq!(u.len(), 4);
+        assert_eq!(u["a"].links.len(), 3);
+        assert_eq!(u["a"].domain, "geography");
+
+        let h = d.highlights();
+        assert_eq!(h.bubbles["a"], "changed");
+        assert_eq!(h.bubbles["c"], "removed");
+        assert_eq!(h.links[&("a".to_string(), "c".to_string())], "link-removed");
+        assert_eq!(h.links[&("a".to_string(), "b".to_string())], "link-changed");
+    }
+}
@@ -1,6 +1,6 @@
 //! The graph is a set of vertices and links between these vertices
 use crate::data::Dataset;
-use std::collections::{HashSet, HashMap};
+use std::collections::{HashSet, HashMap, VecDeque};
 use crate::settings::Settings;
 
 /// The parameters of the model
@@ -29,7 +29,12 @@ pub struct Model {
 pub struct Graph {
     pub n: usize,
     values: HashMap<String, usize>,
-    pub edges: Vec<Edge>
+    names: Vec<String>,
+    edges: Vec<Edge>,
+    /// The indices of the edges from each vertex
+    outgoing: Vec<Vec<usize>>,
+    /// The indices of the edges to each vertex
+    incoming: Vec<Vec<usize>>
 }
 
 impl Graph {
@@ -38,7 +43,10 @@ impl Graph {
         Graph {
             n: 0,
             values: HashMap::new(),
-            edges : Vec::new()
+            names: Vec::new(),
+            edges : Vec::new(),
+            outgoing: Vec::new(),
+            incoming: Vec::new()
         }
     }
 
@@ -46,6 +54,9 @@ impl Graph {
     pub fn add_vertex(&mut self, name : &str) -> usize {
         if !self.values.contains_key(name) {
             self.values.insert(name.to_string(), self.n);
+            self.names.push(name.to_string());
+            self.outgoing.push(Vec::new());
+            self.incoming.push(Vec::new());
             self.n += 1;
             self.n - 1
         } else {
@@ -55,19 +66,87 @@ impl Graph {
 
     /// Get the name of a vertex
     pub fn vertex_name(&self, id : usize) -> Option<String> {
-        for (k, v) in self.values.iter() {
-            if *v == id {
-                return Some(k.clone());
+        self.names.get(id).cloned()
+    }
+
+    /// Get the index of a vertex by its name
+    pub fn index_of(&self, name : &str) -> Option<usize> {
+        self.values.get(name).copied()
+    }
+
+    /// Iterate over the index and name of every vertex
+    pub fn vertices(&self) -> impl Iterator<Item=(usize, &str)> {
+        self.names.iter().enumerate().map(|(i, n)| (i, n.as_str()))
+    }
+
+    /// The edges of the graph
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// Add an edge between two vertices of the graph
+    pub fn add_edge(&mut self, edge : Edge) {
+        self.outgoing[edge.src].push(self.edges.len());
+        self.incoming[edge.trg].push(self.edges.len());
+        self.edges.push(edge);
+    }
+
+    /// Rebuild the edges from and to each vertex
+    fn index_edges(&mut self) {
+        self.outgoing = vec![Vec::new(); self.n];
+        self.incoming = vec![Vec::new(); self.n];
+        for (i, edge) in self.edges.iter().enumerate() {
+            self.outgoing[edge.src].push(i);
+            self.incoming[edge.trg].push(i);
+        }
+    }
+
+    /// Iterate over the edges from a vertex
+    pub fn out_edges(&self, id : usize) -> impl Iterator<Item=&Edge> {
+        self.outgoing[id].iter().map(move |&e| &self.edges[e])
+    }
+
+    /// Iterate over the edges to a vertex
+    pub fn in_edges(&self, id : usize) -> impl Iterator<Item=&Edge> {
+        self.incoming[id].iter().map(move |&e| &self.edges[e])
+    }
+
+    /// The number of edges to or from a vertex, where an edge from a vertex
+    /// to itself is counted once
+    pub fn degree(&self, id : usize) -> usize {
+        self.outgoing[id].len() + self.in_edges(id).filter(|e| e.src != id).count()
+    }
+
+    /// The distinct vertices linked to or from a vertex
+    pub fn neighbours(&self, id : usize) -> Vec<usize> {
+        let mut neighbours : Vec<usize> = self.out_edges(id).map(|e| e.trg)
+            .chain(self.in_edges(id).map(|e| e.src))
+            .collect();
+        neighbours.sort();
+        neighbours.dedup();
+        neighbours
+    }
+
+    /// The adjacency lists of all vertices, following edges in `direction`,
+    /// with one entry for each edge in the order the edges were added
+    pub fn adjacency(&self, direction : Direction) -> Vec<Vec<usize>> {
+        let mut adjacent = vec![Vec::new(); self.n];
+        for edge in self.edges.iter() {
+            if direction != Direction::Incoming {
+                adjacent[edge.src].push(edge.trg);
+            }
+            if direction != Direction::Outgoing {
+                adjacent[edge.trg].push(edge.src);
             }
         }
-        return None;
+        adjacent
     }
 
     /// Set the value of certain points
     pub fn set_fixed_points(&self, mut loc : Vec<f64>, 
                     fixed_points : &HashMap<String, (f64, f64)>) -> Vec<f64> {
         for (key, &(x,y)) in fixed_points.iter() {
-            if let Some(id) = self.values.get(key) {
+            if let Some(id) = self.index_of(key) {
                 loc[id * 2] = x;
                 loc[id * 2 + 1] = y;
             }
@@ -79,7 +158,7 @@ impl Graph {
     pub fn zero_fixed_points(&self, mut loc : Vec<f64>, 
                     fixed_points : &HashMap<String, (f64, f64)>) -> Vec<f64> {
         for (key, _) in fixed_points.iter() {
-            if let Some(id) = self.values.get(key) {
+            if let Some(id) = self.index_of(key) {
                 loc[id * 2] = 0.0;
                 loc[id * 2 + 1] = 0.0;
             }
@@ -87,44 +166,72 @@ impl Graph {
         loc
     }
 
-    /// Return a subgraph that is at most X hops from a node
-    pub fn subgraph(&self, dataset : &str, hops : u32) -> Graph {
-        let mut graph = Graph::new();
-        if let Some(d) = self.values.get(dataset) {
-            self.subgraph2(*d, hops, &mut graph);
+    /// Return the subgraph induced by the vertices that are at most `hops`
+    /// links from any of the `seeds`, following links in `direction`
+    pub fn subgraph(&self, seeds : &[String], hops : u32, direction : Direction) -> Graph {
+        let adjacent = self.adjacency(direction);
+
+        let mut distance = vec![None; self.n];
+        let mut order = Vec::new();
+        let mut queue = VecDeque::new();
+        for seed in seeds.iter() {
+            match self.index_of(seed) {
+                Some(v) if distance[v].is_none() => {
+                    distance[v] = Some(0);
+                    order.push(v);
+                    queue.push_back(v);
+                },
+                Some(_) => {},
+                None => eprintln!("Subgraph function was given a dataset name not in the dataset: {}", seed)
+            }
+        }
+        while let Some(v) = queue.pop_front() {
+            let d = distance[v].unwrap_or(0);
+            if d < hops {
+                for &w in adjacent[v].iter() {
+                    if distance[w].is_none() {
+                        distance[w] = Some(d + 1);
+                        order.push(w);
+                        queue.push_back(w);
+                    }
+                }
+            }
+        }
 
-        } else {
-            eprintln!("Subgraph function was given a dataset name not in the dataset
-            returning an empty graph");
+        let mut graph = Graph::new();
+        let mut ids = vec![None; self.n];
+        for &v in order.iter() {
+            ids[v] = Some(graph.add_vertex(&self.names[v]));
+        }
+        for edge in self.edges.iter() {
+            if let (Some(s), Some(t)) = (ids[edge.src], ids[edge.trg]) {
+                graph.add_edge(Edge { src: s, trg: t, ..edge.clone() });
+            }
         }
         graph
     }
 
-    fn subgraph2(&self, d : usize, hops : u32, graph : &mut Graph) {
-        if hops > 0 {
-            for edge in self.edges.iter() {
-                if edge.src == d {
-                    let s = graph.add_vertex(&self.vertex_name(edge.src).unwrap());
-                    let t = graph.add_vertex(&self.vertex_name(edge.trg).unwrap());
-                    let e = Edge::new(s, t);
-                    if !graph.edges.contains(&e) {
-                        graph.edges.push(e)
-                    }
-                    self.subgraph2(edge.trg, hops - 1, graph);
-                } else if edge.trg == d {
-                    let s = graph.add_vertex(&self.vertex_name(edge.src).unwrap());
-                    let t = graph.add_vertex(&self.vertex_name(edge.trg).unwrap());
-                    let e = Edge::new(s, t);
-                    if !graph.edges.contains(&e) {
-                        graph.edges.push(e)
-                    }
-                    self.subgraph2(edge.src, hops - 1, graph);
+    /// Merge each pair of reciprocal edges (A→B and B→A) into a single
+    /// bidirectional edge, so that the pair does not pull twice as hard in 
+    /// the layout
+    pub fn merge_reciprocal_edges(&mut self) {
+        let mut edge_ids : HashMap<(usize, usize), usize> = HashMap::new();
+        let mut edges : Vec<Edge> = Vec::new();
+        for edge in self.edges.drain(..) {
+            match edge_ids.get(&(edge.trg, edge.src)) {
+                Some(&e) if !edges[e].is_bidirectional() => {
+                    edges[e].reverse = Some(edge.value);
+                },
+                _ => {
+                    edge_ids.insert((edge.src, edge.trg), edges.len());
+                    edges.push(edge);
                 }
             }
         }
+        self.edges = edges;
+        self.index_edges();
     }
 
-
     /// Estimate the cost of a given set of locations (`loc`) given parameters
     pub fn cost(&self, loc : &Vec<f64>, m : &Model) -> f64 {
         let mut cost = 0.0;
@@ -242,7 +349,7 @@ fn repulse_cost(x : f64, y : f64, m : &Model) -> f64 {
 }
 
 
-fn repulse_grad(gradient : &mut Vec<f64>, x : f64, y : f64,
+fn repulse_grad(gradient : &mut [f64], x : f64, y : f64,
                 v1 : usize, v2 : usize, m : &Model) {
     let d = (x * x + y * y).sqrt();
     let s = sigma(m.repulse_rigidity * (m.repulse_dist - d));
@@ -257,11 +364,30 @@ fn repulse_grad(gradient : &mut Vec<f64>, x : f64, y : f64,
     }
 }
 
+/// The direction of the links followed when finding the neighbourhood of a
+/// dataset
+#[derive(Clone,Copy,Debug,PartialEq,Default,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Links to and from the dataset
+    #[default]
+    Both,
+    /// Only links to the dataset
+    Incoming,
+    /// Only links from the dataset
+    Outgoing
+}
+
 /// An edge between two vertices
 #[derive(Debug,PartialEq,Clone)]
 pub struct Edge {
     pub src : usize,
-    pub trg : usize
+    pub trg : usize,
+    /// The number of links from the source to the target
+    pub value : i64,
+    /// The number of links from the target to the source, if the reciprocal
+    /// link was merged into this edge
+    pub reverse : Option<i64>
 }
 
 impl Edge {
@@ -269,9 +395,16 @@ impl Edge {
     pub fn new(from : usize, to  : usize) -> Edge {
         Edge {
             src: from,
-            trg: to
+            trg: to,
+            value: 0,
+            reverse: None
         }
     }
+
+    /// Is this edge a merged pair of reciprocal links
+    pub fn is_bidirectional(&self) -> bool {
+        self.reverse.is_some()
+    }
 }
 
 struct Blocking {
@@ -285,8 +418,8 @@ impl Blocking {
     fn create(xs : &Vec<f64>, n_blocks : usize) -> Blocking {
         let mut blocks = Vec::new();
         blocks.resize(n_blocks, Vec::new());
-        for i in 0..n_blocks {
-            blocks[i].resize(n_blocks, Vec::new());
+        for block in blocks.iter_mut() {
+            block.resize(n_blocks, Vec::new());
         }
 
         let mut max = 0.0;
@@ -307,14 +440,14 @@ impl Blocking {
             }
         }
         Blocking {
-            blocks: blocks,
-            block_size: block_size,
-            max : max,
+            blocks,
+            block_size,
+            max,
             n_blocks
         }
     }
 
-    fn nearby<'a>(&'a self, x : f64, y : f64) -> Vec<(usize, f64, f64)> {
+    fn nearby(&self, x : f64, y : f64) -> Vec<(usize, f64, f64)> {
         if x.is_finite() && y.is_finite() {
             let x_id = ((x + self.max) / self.block_size).floor() as usize;
             let y_id = ((y + self.max) / self.block_size).floor() as usize;
@@ -365,33 +498,94 @@ fn relu(x : f64) -> f64 {
 }
 
 
-/// Build the graph from the dataset
-fn build_graph2(data : &HashMap<String, Dataset>) -> Graph {
-    let mut g = Graph::new();
+/// The identifiers of all datasets that link to or are linked from another
+/// dataset in the data
+pub fn linked_datasets(data : &HashMap<String, Dataset>) -> HashSet<String> {
     let mut linked_datasets = HashSet::new();
 
     for dataset in data.values() {
-        if dataset.links.iter().any(|d| data.contains_key(&d.target)) {
+        let is_link = |target : &String| *target != dataset.identifier && data.contains_key(target);
+        if dataset.links.iter().any(|d| is_link(&d.target)) {
             linked_datasets.insert(dataset.identifier.clone());
             for link in dataset.links.iter() {
-                if data.contains_key(&link.target) {
+                if is_link(&link.target) {
                     linked_datasets.insert(link.target.clone());
                 }
             }
         }
     }
+    linked_datasets
+}
+
+/// The identifiers of the datasets that are not included in the graph as they
+/// have no links to or from any other dataset in the data
+pub fn unlinked_datasets(data : &HashMap<String, Dataset>) -> Vec<String> {
+    let linked = linked_datasets(data);
+    let mut unlinked : Vec<String> = data.values()
+        .filter(|d| !linked.contains(&d.identifier))
+        .map(|d| d.identifier.clone())
+        .collect();
+    unlinked.sort();
+    unlinked
+}
+
+/// The links that are drawn as edges of the graph
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub struct LinkFilter {
+    /// Only links with at least this value (number of links) are kept
+    pub min_value : Option<i64>,
+    /// Only this many of the strongest links from each dataset are kept
+    pub max_per_dataset : Option<usize>
+}
+
+impl LinkFilter {
+    /// Filter the links from a dataset, given as targets and values,
+    /// returning the number of links dropped
+    fn apply(&self, links : &mut Vec<(&str, i64)>) -> usize {
+        let before = links.len();
+        if let Some(min) = self.min_value {
+            links.retain(|&(_, value)| value >= min);
+        }
+        if let Some(k) = self.max_per_dataset {
+            links.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            links.truncate(k);
+        }
+        before - links.len()
+    }
+}
+
+/// Build the graph from the dataset, keeping only the links allowed by 
+/// `filter`. Multiple links to the same dataset are combined into a single 
+/// edge and links from a dataset to itself are left out.
+pub fn build_graph2(data : &HashMap<String, Dataset>, filter : &LinkFilter) -> Graph {
+    let mut g = Graph::new();
+    let linked_datasets = linked_datasets(data);
+    let mut dropped = 0;
  
     for dataset in data.values() {
         if linked_datasets.contains(&dataset.identifier) {
-            let v1 = g.add_vertex(&dataset.identifier);
+            let mut links : Vec<(&str, i64)> = Vec::new();
             for link in dataset.links.iter() {
-                if linked_datasets.contains(&link.target) {
-                    let v2 = g.add_vertex(&link.target);
-                    g.edges.push(Edge::new(v1,v2));
+                if link.target != dataset.identifier && linked_datasets.contains(&link.target) {
+                    match links.iter_mut().find(|l| l.0 == link.target) {
+                        Some(l) => l.1 += link.value.get(),
+                        None => links.push((&link.target, link.value.get()))
+                    }
+                }
+            }
+            dropped += filter.apply(&mut links);
+            if !links.is_empty() {
+                let v1 = g.add_vertex(&dataset.identifier);
+                for (target, value) in links {
+                    let v2 = g.add_vertex(target);
+                    g.add_edge(Edge { value, ..Edge::new(v1, v2) });
                 }
             }
         }
     }
+    if dropped > 0 {
+        eprintln!("{} link(s) dropped by `min_link_value` or `max_links_per_dataset`", dropped);
+    }
     g
 }
 
@@ -399,29 +593,42 @@ fn build_graph2(data : &HashMap<String, Dataset>) -> Graph {
 /// Build the graph according to some selection principle
 pub fn build_graph(data : &HashMap<String, Dataset>, 
                    settings : &Settings) -> Graph {
-    match settings.selection {
-        Some(ref ds) if ds == "dataset" && settings.selected.is_some() => {
-            build_graph2(&data).subgraph(
-                &settings.selected.clone().unwrap(),
-                settings.hops.unwrap_or(2))
+    let filter = LinkFilter {
+        min_value: settings.min_link_value,
+        max_per_dataset: settings.max_links_per_dataset
+    };
+    let mut graph = match settings.selection_expr() {
+        None => {
+            build_graph2(data, &filter).subgraph(
+                settings.selected.as_deref().unwrap_or(&[]),
+                settings.hops.unwrap_or(2),
+                settings.direction.unwrap_or_default())
         },
-        Some(ref d) if d == "domain" && settings.selected.is_some() => {
-            let dom = settings.selected.clone().unwrap();
-            let data2 = data.iter().filter(|kv| {
-                kv.1.domain == dom ||
-                    settings.datasets.iter().map(|x| x.contains(kv.0)).
-                        next().unwrap_or(false)
-            }).map(|x| (x.0.clone(), x.1.clone())).collect();
-            build_graph2(&data2)
-        },
-        _ => build_graph2(&data)
+        Some(expr) => build_graph2(&expr.select(data), &filter)
+    };
+    if settings.merge_reciprocal.unwrap_or(false) {
+        graph.merge_reciprocal_edges();
+    }
+    graph
+}
+
+/// The datasets selected (according to the settings) that are not in the 
+/// graph as they have no links. This is empty when a dataset is selected as 
+/// the graph then only contains its neighbourhood.
+pub fn unlinked_selected(data : &HashMap<String, Dataset>,
+                         settings : &Settings) -> Vec<String> {
+    match settings.selection_expr() {
+        None => Vec::new(),
+        Some(expr) => unlinked_datasets(&expr.select(data))
     }
 }
  
 
 #[cfg(test)]
 mod tests {
-    use graph::{sigma,relu};
+    use crate::data::{Dataset, IntLike, Link};
+    use crate::graph::{sigma,relu,build_graph2,Graph,Edge,Direction,LinkFilter};
+    use std::collections::HashMap;
 
     #[test]
     fn test_sigma() {
@@ -439,5 +646,126 @@ mod tests {
             assert_eq!(relu(i as f64), i as f64);
         }
     }
-}
 
+    fn path_graph() -> Graph {
+        // a -> b -> c -> d, e -> b, c -> a
+        let mut g = Graph::new();
+        let a = g.add_vertex("a");
+        let b = g.add_vertex("b");
+        let c = g.add_vertex("c");
+        let d = g.add_vertex("d");
+        let e = g.add_vertex("e");
+        g.add_edge(Edge::new(a, b));
+        g.add_edge(Edge::new(b, c));
+        g.add_edge(Edge::new(c, d));
+        g.add_edge(Edge::new(e, b));
+        g.add_edge(Edge::new(c, a));
+        g
+    }
+
+    #[test]
+    fn test_subgraph() {
+        let g = path_graph();
+        let s = g.subgraph(&["a".to_string()], 1, Direction::Both);
+        assert_eq!(s.n, 3);
+        // The induced subgraph includes the edge b -> c
+        assert_eq!(s.edges.len(), 3);
+        assert_eq!(s.vertex_name(0), Some("a".to_string()));
+
+        let s = g.subgraph(&["a".to_string()], 2, Direction::Outgoing);
+        assert_eq!(s.n, 3);
+        assert!(s.vertex_name(3).is_none());
+
+        let s = g.subgraph(&["b".to_string()], 1, Direction::Incoming);
+        assert_eq!(s.n, 3);
+        assert_eq!(s.edges.len(), 2);
+
+        let s = g.subgraph(&["d".to_string(), "e".to_string(), "x".to_string()], 0, Direction::Both);
+        assert_eq!(s.n, 2);
+        assert_eq!(s.edges.len(), 0);
+    }
+
+    #[test]
+    fn test_accessors() {
+        let g = path_graph();
+        assert_eq!(g.index_of("c"), Some(2));
+        assert_eq!(g.index_of("x"), None);
+        assert_eq!(g.vertex_name(4), Some("e".to_string()));
+        assert_eq!(g.vertices().map(|(_, n)| n).collect::<Vec<_>>(), vec!["a", "b", "c", "d", "e"]);
+        assert_eq!(g.out_edges(2).map(|e| e.trg).collect::<Vec<_>>(), vec![3, 0]);
+        assert_eq!(g.in_edges(1).map(|e| e.src).collect::<Vec<_>>(), vec![0, 4]);
+        assert_eq!(g.degree(1), 3);
+        assert_eq!(g.neighbours(0), vec![1, 2]);
+        assert_eq!(g.adjacency(Direction::Outgoing)[2], vec![3, 0]);
+        assert_eq!(g.adjacency(Direction::Both)[0], vec![1, 2]);
+        // Edges to and from a vertex are listed in the order they were added
+        assert_eq!(g.adjacency(Direction::Both)[1], vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn test_merge_reciprocal_edges() {
+        let mut g = path_graph();
+        g.edges.iter_mut().for_each(|e| e.value = 1);
+        g.add_edge(Edge { value: 5, ..Edge::new(1, 0) });
+        g.merge_reciprocal_edges();
+        assert_eq!(g.edges.len(), 5);
+        assert_eq!(g.edges[0], Edge { src: 0, trg: 1, value: 1, reverse: Some(5) });
+        assert_eq!(g.edges.iter().filter(|e| e.is_bidirectional()).count(), 1);
+        // The merged edge is no longer an edge into vertex 0
+        assert_eq!(g.in_edges(0).map(|e| e.src).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(g.neighbours(0), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_build_graph() {
+        let mut data = HashMap::new();
+        for (s, links) in [("a", vec!["a", "b", "b"]), ("b", vec!["a", "x"]), ("c", vec!["c"])] {
+            data.insert(s.to_string(), Dataset {
+                identifier: s.to_string(),
+                links: links.iter().map(|t| Link {
+                    target: t.to_string(),
+                    value: IntLike::from(10)
+                }).collect(),
+                ..Default::default()
+            });
+        }
+        let g = build_graph2(&data, &LinkFilter::default());
+        assert_eq!(g.n, 2);
+        assert!(g.index_of("c").is_none());
+        assert_eq!(g.edges.len(), 2);
+        let a = g.index_of("a").unwrap();
+        assert_eq!(g.out_edges(a).map(|e| e.value).collect::<Vec<_>>(), vec![20]);
+
+        let g = build_graph2(&data, &LinkFilter { min_value: Some(15), max_per_dataset: None });
+        assert_eq!(g.n, 2);
+        assert_eq!(g.edges.len(), 1);
+        let g = build_graph2(&data, &LinkFilter { min_value: Some(25), max_per_dataset: None });
+        assert_eq!(g.n, 0);
+    }
+
+    #[test]
+    fn test_link_filter() {
+        let filter = LinkFilter { min_value: Some(2), max_per_dataset: Some(2) };
+        let mut links = vec![("a", 1), ("b", 5), ("c", 3), ("d", 5)];
+        assert_eq!(filter.apply(&mut links), 2);
+        assert_eq!(links, vec![("b", 5), ("d", 5)]);
+    }
+
+    #[test]
+    fn test_subgraph_dense() {
+        let mut g = Graph::new();
+        for i in 0..200 {
+            g.add_vertex(&i.to_string());
+        }
+        for i in 0..200 {
+            for j in 0..200 {
+                if i != j {
+                    g.add_edge(Edge::new(i, j));
+                }
+            }
+        }
+        let s = g.subgraph(&["0".to_string()], 10, Direction::Both);
+        assert_eq!(s.n, 200);
+        assert_eq!(s.edges.len(), 200 * 199);
+    }
+}
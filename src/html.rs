@@ -0,0 +1,239 @@
+//! Methods for outputting graphs as a self-contained interactive HTML page,
+//! where the layout is embedded as JSON and drawn by an offline viewer
+use crate::scene::{Logo, Renderer, Scene, ARROW_LENGTH};
+use htmlescape::{encode_attribute, encode_minimal};
+use std::io::Write;
+
+/// The page of the viewer, with `{{title}}`, `{{footer}}` and `{{data}}` to
+/// be filled in
+const VIEWER : &str = include_str!("viewer.html");
+
+/// The layout of the cloud as read by the viewer
+#[derive(Debug,Serialize)]
+struct Cloud {
+    /// The width and height of the cloud
+    size : f64,
+    legend : Vec<LegendItem>,
+    datasets : Vec<Bubble>,
+    links : Vec<Line>,
+    /// Whether links end in arrowheads
+    arrows : bool,
+    arrow_length : f64,
+    /// Examples of the weights of links
+    weights : Vec<WeightItem>
+}
+
+#[derive(Debug,Serialize)]
+struct LegendItem {
+    title : String,
+    colour : String
+}
+
+#[derive(Debug,Serialize)]
+struct WeightItem {
+    value : i64,
+    width : Option<f64>,
+    opacity : Option<f64>
+}
+
+#[derive(Debug,Serialize)]
+struct Bubble {
+    identifier : String,
+    title : String,
+    /// The lines of the label
+    label : Vec<String>,
+    label_size : f64,
+    description : Option<String>,
+    domain : String,
+    /// The index of the legend entry of the dataset, if any
+    group : Option<usize>,
+    colour : String,
+    triples : i64,
+    url : Option<String>,
+    x : f64,
+    y : f64,
+    r : f64,
+    /// Any extra class of the bubble
+    class : Option<String>
+}
+
+#[derive(Debug,Serialize)]
+struct Line {
+    source : usize,
+    target : usize,
+    x1 : f64,
+    y1 : f64,
+    x2 : f64,
+    y2 : f64,
+    /// The control point of a curved link
+    control : Option<(f64, f64)>,
+    /// The points a bundled link passes through
+    bundle : Vec<(f64, f64)>,
+    value : i64,
+    reverse : Option<i64>,
+    bidirectional : bool,
+    class : Option<String>,
+    /// The stroke width of a weighted link
+    width : Option<f64>,
+    /// The opacity of a weighted link
+    opacity : Option<f64>
+}
+
+/// Writes scenes as HTML pages with a title
+pub struct HtmlRenderer {
+    pub title : String
+}
+
+impl Renderer for HtmlRenderer {
+    fn render(&self, scene : &Scene, out : &mut dyn Write) -> std::result::Result<(), String> {
+        let cloud = layout(scene);
+        // The data must not close the script element it is in
+        let json = serde_json::to_string(&cloud)
+            .expect("Could not serialize cloud")
+            .replace("</", "<\\/");
+        write!(out, "{}", fill(VIEWER, &[
+            ("title", encode_minimal(&self.title)),
+            ("footer", footer(scene)),
+            ("data", json)
+        ])).map_err(|e| format!("Could not write HTML: {}", e))
+    }
+}
+
+/// The positions, sizes and colours of the bubbles and the links between them
+fn layout(scene : &Scene) -> Cloud {
+    Cloud {
+        size: scene.size,
+        legend: scene.legend.iter().filter(|e| e.availability.is_none()).map(|e| LegendItem {
+            title: e.title.clone(),
+            colour: e.colour.clone()
+        }).collect(),
+        datasets: scene.bubbles.iter().map(|b| Bubble {
+            identifier: b.identifier.clone(),
+            title: b.title.clone(),
+            label: b.label.clone(),
+            label_size: b.label_size,
+            description: b.description.clone(),
+            domain: b.domain.clone(),
+            group: b.group,
+            colour: b.colour.clone(),
+            triples: b.triples,
+            url: b.url.clone(),
+            x: b.x,
+            y: b.y,
+            r: b.r,
+            class: b.highlight.clone()
+        }).collect(),
+        links: scene.lines.iter().map(|l| Line {
+            source: l.source,
+            target: l.target,
+            x1: l.x1,
+            y1: l.y1,
+            x2: l.x2,
+            y2: l.y2,
+            control: l.control,
+            bundle: l.bundle.iter().map(|&(x, y)| ((x * 100.0).round() / 100.0, (y * 100.0).round() / 100.0)).collect(),
+            value: l.value,
+            reverse: l.reverse,
+            bidirectional: l.is_bidirectional(),
+            class: l.highlight.clone(),
+            width: l.weight.width,
+            opacity: l.weight.opacity
+        }).collect(),
+        arrows: scene.arrows,
+        arrow_length: ARROW_LENGTH,
+        weights: scene.weight_legend.iter().map(|e| WeightItem {
+            value: e.value,
+            width: e.weight.width,
+            opacity: e.weight.opacity
+        }).collect()
+    }
+}
+
+/// The rights text and logo
+fn footer(scene : &Scene) -> String {
+    let rights = scene.rights_text.as_ref()
+        .map(|rt| format!("<p class=\"rights\">{}</p>", rt))
+        .unwrap_or_default();
+    let logo = match scene.logo {
+        Logo::Image { ref href, width } => format!("<img class=\"logo\" src=\"{}\" alt=\"Logo\" width=\"{}\">",
+                                                   encode_attribute(href), width),
+        Logo::Default => include_str!("by.svg").to_string()
+    };
+    format!("{}\n{}", rights, logo)
+}
+
+/// Replace each `{{key}}` in a template by its value
+fn fill(template : &str, values : &[(&str, String)]) -> String {
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let key_end = rest[start..].find("}}").map(|e| start + e);
+        match key_end.and_then(|end| {
+            values.iter().find(|(k, _)| *k == &rest[start + 2..end]).map(|(_, v)| (end, v))
+        }) {
+            Some((end, value)) => {
+                result.push_str(value);
+                rest = &rest[end + 2..];
+            },
+            None => {
+                result.push_str("{{");
+                rest = &rest[start + 2..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::html::*;
+    use crate::data::Dataset;
+    use crate::graph::{Edge, Graph};
+    use crate::scene::Highlights;
+    use crate::settings::Settings;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_fill() {
+        assert_eq!(fill("<h1>{{title}}</h1>{{data}} {{other}}", &[
+            ("title", "A {{data}}".to_string()),
+            ("data", "[]".to_string())
+        ]), "<h1>A {{data}}</h1>[] {{other}}");
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut data = HashMap::new();
+        for (id, domain) in [("a", "media"), ("b", "geography")] {
+            data.insert(id.to_string(), Dataset {
+                identifier: id.to_string(),
+                domain: domain.to_string(),
+                ..Default::default()
+            });
+        }
+        let mut graph = Graph::new();
+        graph.add_vertex("a");
+        graph.add_vertex("b");
+        graph.add_edge(Edge::new(0, 1));
+        let settings : Settings = serde_json::from_str(r##"{
+            "legend": [{"title": "Geography", "domain": "geography", "colour": "#29c9cc"}],
+            "fixed_points": {},
+            "show_availability": true
+        }"##).unwrap();
+        let scene = Scene::new(&graph, &vec![-10.0, 0.0, 10.0, 5.0], &data, 5.0,
+                               &settings, &Highlights::default());
+        let cloud = layout(&scene);
+        assert_eq!(cloud.datasets.len(), 2);
+        assert_eq!(cloud.links.len(), 1);
+        assert_eq!(cloud.legend.len(), 1);
+        assert_eq!(cloud.datasets[1].group, Some(0));
+        assert_eq!(cloud.size, 21.0);
+        let mut out = Vec::new();
+        HtmlRenderer { title: "A & B".to_string() }.render(&scene, &mut out).unwrap();
+        let page = String::from_utf8(out).unwrap();
+        assert!(page.contains("A &amp; B"));
+        assert!(!page.contains("{{data}}"));
+    }
+}
@@ -6,11 +6,11 @@ use noisy_float::prelude::*;
 /// Find the domain by the neighbours of a dataset, the domain will be
 /// set to the most frequent among neighbours
 pub fn domain_by_most_neighbours(datasets : &mut HashMap<String, Dataset>) {
-    let mut incoming = HashMap::new();
+    let mut incoming : HashMap<String, Vec<String>> = HashMap::new();
     for (_, dataset) in datasets.iter() {
         for link in dataset.links.iter() {
             incoming.entry(link.target.clone())
-                .or_insert_with(|| Vec::new()).push(dataset.identifier.clone());
+                .or_default().push(dataset.identifier.clone());
         }
     }
     let mut ds2domain : HashMap<String, String> = datasets.iter().map(|k| {
@@ -22,11 +22,11 @@ pub fn domain_by_most_neighbours(datasets : &mut HashMap<String, Dataset>) {
         last_fails = fails;
         fails = 0;
         for (_, dataset) in datasets.iter_mut() {
-            if dataset.domain == "" {
+            if dataset.domain.is_empty() {
                 let mut counts = HashMap::new();
                 for link in dataset.links.iter() {
                     match ds2domain.get(&link.target) {
-                        Some(d) if d != "" => {
+                        Some(d) if !d.is_empty() => {
                             let c : i32 = *counts.get(d).unwrap_or(&0);
                             counts.insert(d.clone(), c + 1);
                         },
@@ -35,9 +35,9 @@ pub fn domain_by_most_neighbours(datasets : &mut HashMap<String, Dataset>) {
                 }
 
                 let empty = Vec::new();
-                for link in incoming.get(&dataset.identifier).unwrap_or_else(|| &empty).iter() {
+                for link in incoming.get(&dataset.identifier).unwrap_or(&empty).iter() {
                     match ds2domain.get(link) {
-                        Some(d) if d != "" => {
+                        Some(d) if !d.is_empty() => {
                             let c : i32 = *counts.get(d).unwrap_or(&0);
                             counts.insert(d.clone(), c + 1);
                         },
@@ -53,7 +53,7 @@ pub fn domain_by_most_neighbours(datasets : &mut HashMap<String, Dataset>) {
                         best_count = *v;
                     }
                 }
-                if best_domain == "" {
+                if best_domain.is_empty() {
                     fails += 1;
                 } else {
                     dataset.domain = best_domain.to_string();
@@ -72,17 +72,17 @@ const ALPHA : f64 = 0.0001f64;
 /// is created from the labelled datasets and this is applied to all the 
 /// unlabelled datasets
 pub fn domain_by_keywords(datasets : &mut HashMap<String, Dataset>) {
-    let mut tag_cat_freq = HashMap::new();
+    let mut tag_cat_freq : HashMap<String, HashMap<String, i32>> = HashMap::new();
     let mut cat_freq = HashMap::new();
     let mut tag_freq = HashMap::new();
     let mut total = 0;
 
     for (_, dataset) in datasets.iter() {
         let cat = dataset.domain.clone();
-        if cat != "" {
+        if !cat.is_empty() {
             let c = *cat_freq.get(&cat).unwrap_or(&0);
             for tag in dataset.keywords.iter() {
-                let x = tag_cat_freq.entry(tag.clone()).or_insert_with(|| HashMap::new());
+                let x = tag_cat_freq.entry(tag.clone()).or_default();
                 let c2 = *x.get(&cat).unwrap_or(&0);
                 x.insert(cat.clone(), c2 + 1);
                 let c3 = *tag_freq.get(tag).unwrap_or(&0);
@@ -114,10 +114,10 @@ pub fn domain_by_keywords(datasets : &mut HashMap<String, Dataset>) {
         (cf.0.clone(), ((*cf.1 as f64 + ALPHA) / (total as f64 + n_alpha)).ln())
     }).collect();
 
-    let cats : Vec<String> = cat_prob.keys().map(|x| x.clone()).collect();
+    let cats : Vec<String> = cat_prob.keys().cloned().collect();
 
     for (_, dataset) in datasets.iter_mut() {
-        if dataset.domain == "" {
+        if dataset.domain.is_empty() {
             if let Some((cat, _p)) = cats.iter().map(|c| {
                 let mut prob = *cat_prob.get(c).unwrap_or(&(ALPHA / (total as f64 + n_alpha)));
                 for tag in dataset.keywords.iter() {
@@ -134,8 +134,8 @@ pub fn domain_by_keywords(datasets : &mut HashMap<String, Dataset>) {
 #[cfg(test)]
 mod tests{
     use std::collections::HashMap;
-    use data::{Dataset,IntLike};
-    use ident::*;
+    use crate::data::{Dataset,IntLike};
+    use crate::ident::*;
 
     fn make_dataset(s : &str, d : &str) -> Dataset {
         Dataset {
@@ -145,7 +145,8 @@ mod tests{
             identifier: s.to_string(),
             domain: d.to_string(),
             triples: IntLike::from(0),
-            keywords : s.chars().map(|x| x.to_string()).collect()
+            keywords : s.chars().map(|x| x.to_string()).collect(),
+            ..Default::default()
         }
     }
 
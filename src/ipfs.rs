@@ -0,0 +1,94 @@
+//! Annotation of the downloads of datasets with their mirrors on IPFS
+//!
+//! The mirrors are read from a CSV file where each line consists of the
+//! content identifier (CID) of the file on IPFS and the download URL, e.g.,
+//!
+//! ```text
+//! QmahzdBn6KAzC1eejsBftx8EvmUK62vniPEU8zKgjrTUe7,http://dbpedia.org/data/Berlin.n3
+//! ```
+use crate::check::AccessKind;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Result};
+
+/// Read the IPFS hashes file as a map from URLs to CIDs
+pub fn read_hashes<R : Read>(input : R) -> Result<HashMap<String, String>> {
+    let mut hashes = HashMap::new();
+    for line in BufReader::new(input).lines() {
+        let line = line?;
+        if let Some((cid, url)) = line.trim().split_once(',') {
+            if !cid.is_empty() && !url.is_empty() {
+                hashes.insert(url.trim().to_string(), cid.trim().to_string());
+            }
+        }
+    }
+    Ok(hashes)
+}
+
+/// Mark every download whose URL has a CID in `hashes` as mirrored on IPFS.
+/// The access point's `mirror` is set to `["ipfs:<cid>"]` and its `status` to
+/// "OK". Returns the number of access points annotated.
+pub fn annotate(data : &mut Map<String, Value>, hashes : &HashMap<String, String>) -> usize {
+    let mut annotated = 0;
+    for dataset in data.values_mut() {
+        for kind in [AccessKind::FullDownload, AccessKind::OtherDownload] {
+            if let Some(entries) = dataset.get_mut(kind.field()).and_then(|l| l.as_array_mut()) {
+                for entry in entries.iter_mut().filter_map(|e| e.as_object_mut()) {
+                    let cid = entry.get(kind.url_field())
+                        .and_then(|u| u.as_str())
+                        .and_then(|u| hashes.get(u));
+                    if let Some(cid) = cid {
+                        let mirror = Value::Array(vec![Value::String(format!("ipfs:{}", cid))]);
+                        entry.insert("mirror".to_string(), mirror);
+                        entry.insert("status".to_string(), Value::String("OK".to_string()));
+                        annotated += 1;
+                    }
+                }
+            }
+        }
+    }
+    annotated
+}
+
+/// Remove all datasets that have no download with a mirror
+pub fn retain_mirrored(data : &mut Map<String, Value>) {
+    data.retain(|_, dataset| {
+        [AccessKind::FullDownload, AccessKind::OtherDownload].iter().any(|kind| {
+            dataset.get(kind.field())
+                .and_then(|l| l.as_array())
+                .map(|l| l.iter().any(|e| {
+                    e.get("mirror")
+                        .and_then(|m| m.as_array())
+                        .map(|m| !m.is_empty())
+                        .unwrap_or(false)
+                }))
+                .unwrap_or(false)
+        })
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ipfs::*;
+
+    #[test]
+    fn test_annotate() {
+        let hashes = read_hashes("QmA,http://a.org/dump.nt\nQmB,http://b.org/x.rdf\n\n".as_bytes()).unwrap();
+        assert_eq!(hashes.len(), 2);
+        let mut data : Map<String, Value> = serde_json::from_str(r#"{
+            "a": { "full_download": [{ "download_url": "http://a.org/dump.nt", "status": "FAIL (404)" }] },
+            "b": { "other_download": [{ "access_url": "http://b.org/x.rdf", "mirror": [] },
+                                      { "access_url": "http://b.org/y.rdf" }] },
+            "c": { "other_download": [{ "access_url": "http://c.org/", "mirror": [] }] }
+        }"#).unwrap();
+
+        assert_eq!(annotate(&mut data, &hashes), 2);
+        assert_eq!(data["a"]["full_download"][0]["mirror"][0], "ipfs:QmA");
+        assert_eq!(data["a"]["full_download"][0]["status"], "OK");
+        assert_eq!(data["b"]["other_download"][0]["mirror"][0], "ipfs:QmB");
+        assert!(data["b"]["other_download"][1].get("mirror").is_none());
+
+        retain_mirrored(&mut data);
+        assert_eq!(data.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,235 @@
+//! The labels of the bubbles, made from the titles (or identifiers) of the
+//! datasets by one of several strategies. Text is measured by its display
+//! width and only cut between graphemes, so that titles in any script keep
+//! whole characters
+use crate::scene::LABEL_SIZE;
+use crate::settings::Settings;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// The average width of a character relative to the font size
+const CHAR_WIDTH : f64 = 0.6;
+/// The height of a line of a label relative to the font size
+pub const LINE_SPACING : f64 = 1.2;
+/// The part of the width of a bubble that a label may fill
+const PADDING : f64 = 0.9;
+/// The smallest font size that labels are shrunk to
+const MIN_LABEL_SIZE : f64 = 3.0;
+/// The fewest characters a line of a wrapped label may have, even if it
+/// overflows the bubble
+const MIN_COLUMNS : usize = 10;
+/// The fewest characters an abbreviated label may have
+const MIN_ABBREVIATION : usize = 12;
+const ELLIPSIS : &str = "…";
+
+/// How the title of a dataset is fitted to its bubble
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelStrategy {
+    /// The first six characters and "..." for titles of more than nine
+    Truncate,
+    /// The title wrapped over several lines inside the bubble
+    Wrap,
+    /// The title cut after the last word that fits the bubble
+    Abbreviate,
+    /// The identifier of the dataset
+    Identifier,
+    /// The whole title, in a smaller font if needed to fit the bubble
+    Fit
+}
+
+/// The lines of a label and their font size
+#[derive(Clone,Debug,PartialEq)]
+pub struct Label {
+    pub lines : Vec<String>,
+    pub size : f64
+}
+
+/// The label of a dataset with a bubble of the given radius
+pub fn bubble_label(title : &str, identifier : &str, radius : f64, settings : &Settings) -> Label {
+    if settings.min_label_radius.map(|r| radius < r).unwrap_or(false) {
+        return Label { lines: Vec::new(), size: LABEL_SIZE };
+    }
+    let strategy = settings.label.unwrap_or(LabelStrategy::Truncate);
+    let (lines, size) = match strategy {
+        LabelStrategy::Truncate => (vec![truncate(title)], LABEL_SIZE),
+        LabelStrategy::Wrap => {
+            let max_lines = settings.label_lines.unwrap_or(2).max(1);
+            // The lines must fit the chord of the circle at the top and
+            // bottom of the text
+            let half_height = max_lines as f64 * LABEL_SIZE * LINE_SPACING / 2.0;
+            let chord = 2.0 * (radius * radius - half_height * half_height).max(0.0).sqrt();
+            (wrap(title, columns(chord, LABEL_SIZE).max(MIN_COLUMNS), max_lines), LABEL_SIZE)
+        },
+        LabelStrategy::Abbreviate => {
+            (vec![abbreviate(title, columns(2.0 * radius, LABEL_SIZE).max(MIN_ABBREVIATION))], LABEL_SIZE)
+        },
+        LabelStrategy::Identifier => (vec![identifier.to_string()], LABEL_SIZE),
+        LabelStrategy::Fit => {
+            let width = title.width() as f64 * CHAR_WIDTH;
+            let size = (2.0 * radius * PADDING / width).min(LABEL_SIZE);
+            if size >= MIN_LABEL_SIZE {
+                (vec![title.to_string()], size)
+            } else {
+                (vec![abbreviate(title, columns(2.0 * radius, MIN_LABEL_SIZE))], MIN_LABEL_SIZE)
+            }
+        }
+    };
+    Label { lines, size }
+}
+
+/// The number of characters that fit in a width at a font size
+fn columns(width : f64, size : f64) -> usize {
+    (width * PADDING / (size * CHAR_WIDTH)).floor().max(0.0) as usize
+}
+
+/// Shorten a title to its first six characters and "..." if it is longer
+/// than nine characters
+fn truncate(text : &str) -> String {
+    if text.graphemes(true).count() > 9 {
+        let mut s = text.graphemes(true).take(6).collect::<String>();
+        s.push_str("...");
+        s
+    } else {
+        text.to_string()
+    }
+}
+
+/// Cut a text to a width, ending with an ellipsis
+fn cut(text : &str, columns : usize) -> String {
+    let mut s = String::new();
+    for g in text.graphemes(true) {
+        if s.width() + g.width() + ELLIPSIS.width() > columns {
+            break;
+        }
+        s.push_str(g);
+    }
+    let mut s = s.trim_end().to_string();
+    s.push_str(ELLIPSIS);
+    s
+}
+
+/// Split a text after each space, hyphen or slash, which are where the text
+/// may be broken
+fn chunks(text : &str) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    for (i, token) in text.split_word_bound_indices() {
+        if token.chars().all(char::is_whitespace) || token == "-" || token == "/" {
+            chunks.push(&text[start..i + token.len()]);
+            start = i + token.len();
+        }
+    }
+    if start < text.len() {
+        chunks.push(&text[start..]);
+    }
+    chunks
+}
+
+/// Cut a text after the last word that fits the width
+fn abbreviate(text : &str, columns : usize) -> String {
+    if text.width() <= columns {
+        return text.to_string();
+    }
+    let mut s = String::new();
+    for chunk in chunks(text) {
+        if (s.clone() + chunk).trim_end().width() + ELLIPSIS.width() > columns {
+            break;
+        }
+        s.push_str(chunk);
+    }
+    let s = s.trim_end().trim_end_matches(['-', '/', ',', ':', ';']);
+    if s.is_empty() {
+        cut(text, columns)
+    } else {
+        format!("{}{}", s, ELLIPSIS)
+    }
+}
+
+/// Break a text into at most `max_lines` lines of a width. A word may be up
+/// to half as long again as a line, and only longer words are broken. If the
+/// text does not fit, the last line ends with an ellipsis
+fn wrap(text : &str, columns : usize, max_lines : usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for chunk in chunks(text) {
+        if !line.is_empty() && (line.clone() + chunk).trim_end().width() > columns {
+            lines.push(line.trim_end().to_string());
+            line = String::new();
+        }
+        line.push_str(chunk);
+        // Words that are much longer than a line are broken between graphemes
+        while line.trim_end().width() > columns * 3 / 2 {
+            let mut head = String::new();
+            for g in line.graphemes(true) {
+                if head.width() + g.width() > columns && !head.is_empty() {
+                    break;
+                }
+                head.push_str(g);
+            }
+            line = line[head.len()..].to_string();
+            lines.push(head);
+        }
+    }
+    if !line.trim_end().is_empty() {
+        lines.push(line.trim_end().to_string());
+    }
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        let last = lines.pop().unwrap_or_default();
+        if last.width() + ELLIPSIS.width() <= columns {
+            lines.push(last + ELLIPSIS);
+        } else {
+            lines.push(cut(&last, columns));
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::label::*;
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("DBpedia-NL"), "DBpedi...");
+        assert_eq!(truncate("Ελληνικά"), "Ελληνικά");
+        assert_eq!(truncate("Données ouvertes"), "Donnée...");
+    }
+
+    #[test]
+    fn test_abbreviate() {
+        assert_eq!(abbreviate("DBpedia-NL", 12), "DBpedia-NL");
+        assert_eq!(abbreviate("Linked Open Data Cloud", 12), "Linked Open…");
+        assert_eq!(abbreviate("Linked-Open-Data", 10), "Linked…");
+        assert_eq!(abbreviate("Bibliothèquenationale", 8), "Bibliot…");
+        assert_eq!(abbreviate("日本語のデータセット", 12), "日本語のデ…");
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("Linked Open Data Cloud", 12, 2), vec!["Linked Open", "Data Cloud"]);
+        assert_eq!(wrap("Linked Open Data Cloud", 6, 2), vec!["Linked", "Open…"]);
+        assert_eq!(wrap("Linked Open Data Cloud", 12, 1), vec!["Linked Open…"]);
+        assert_eq!(wrap("DBpedia-NL", 8, 3), vec!["DBpedia-", "NL"]);
+        assert_eq!(wrap("Überdatenbanksystem", 8, 3), vec!["Überdate", "nbanksystem"]);
+        assert_eq!(wrap("Association for Computing", 10, 2), vec!["Association", "for…"]);
+        assert_eq!(wrap(&"e\u{301}".repeat(5), 2, 2), vec!["e\u{301}e\u{301}", "e\u{301}e\u{301}e\u{301}"]);
+    }
+
+    #[test]
+    fn test_bubble_label() {
+        let mut settings : Settings = serde_json::from_str(r##"{
+            "legend": [],
+            "fixed_points": {},
+            "label": "fit",
+            "min_label_radius": 10
+        }"##).unwrap();
+        let label = bubble_label("A long title of a dataset", "a", 30.0, &settings);
+        assert_eq!(label.lines, vec!["A long title of a dataset"]);
+        assert!(label.size < LABEL_SIZE && label.size >= MIN_LABEL_SIZE);
+        assert!(bubble_label("Title", "a", 5.0, &settings).lines.is_empty());
+        settings.label = Some(LabelStrategy::Identifier);
+        assert_eq!(bubble_label("Title", "dbpedia-nl", 15.0, &settings).lines, vec!["dbpedia-nl"]);
+    }
+}
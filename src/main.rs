@@ -8,19 +8,37 @@ extern crate clap;
 extern crate htmlescape;
 extern crate noisy_float;
 extern crate rand;
+extern crate regex;
+extern crate ureq;
 
+mod bundling;
+mod centrality;
+mod check;
+mod community;
 mod data;
+mod diff;
 mod graph;
+mod html;
 mod ident;
+mod ipfs;
+mod label;
+mod pdf;
+mod raster;
+mod scene;
+mod selection;
 mod settings;
+mod stats;
 mod svg;
+mod tikz;
 mod tree;
 
-use clap::{Arg, App, ArgMatches};
-use crate::data::Dataset;
+use clap::{Arg, App, AppSettings, ArgMatches, SubCommand};
+use crate::data::{Dataset, MergeStrategy};
 use crate::settings::Settings;
+use crate::tree::UnlinkedLayout;
 use std::collections::HashMap;
 use std::fs::File;
+use std::io::BufWriter;
 use rand::Rng;
 use std::process::exit;
 use argmin::core::observers::{ObserverMode, SlogLogger};
@@ -29,6 +47,171 @@ use argmin::solver::linesearch::MoreThuenteLineSearch;
 use argmin::solver::quasinewton::LBFGS;
 use argmin::solver::gradientdescent::SteepestDescent;
 
+/// The arguments for the layout algorithm
+fn layout_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("spring")
+            .short("s")
+            .long("spring")
+            .value_name("FORCE")
+            .help("The value of the spring force")
+            .takes_value(true),
+        Arg::with_name("repulse")
+            .short("r")
+            .long("repulse")
+            .value_name("FORCE")
+            .help("The value of the repulsion force")
+            .takes_value(true),
+        Arg::with_name("repulse_dist")
+            .short("d")
+            .long("distance")
+            .value_name("PIXELS")
+            .help("The minimal distance between bubbles")
+            .takes_value(true),
+        Arg::with_name("repulse_rigidity")
+            .long("repulse-rigidity")
+            .value_name("FACTOR")
+            .help("The rigidity of repulsion between bubbles")
+            .takes_value(true),
+        Arg::with_name("canvas")
+            .short("w")
+            .long("well")
+            .value_name("FORCE")
+            .help("The value of the well boundary force")
+            .takes_value(true),
+        Arg::with_name("canvas_size")
+            .short("c")
+            .long("canvas")
+            .value_name("PIXELS")
+            .help("The radius of the circle that the bubbles should be contained in")
+            .takes_value(true),
+        Arg::with_name("canvas_rigidity")
+            .long("canvas-rigidity")
+            .value_name("FACTOR")
+            .help("The rigidity of the well")
+            .takes_value(true),
+        Arg::with_name("algorithm")
+            .long("algorithm")
+            .value_name("lbfgs|sd")
+            .help("The algorithm used to find the cloud diagram (lbfgs = Limited BFGS, sd = Steepest Descent)")
+            .takes_value(true),
+        Arg::with_name("max_iters")
+            .short("i")
+            .long("max-iters")
+            .value_name("ITERATIONS")
+            .help("The maximum number of iterations to perform (default=10000)")
+            .takes_value(true),
+        Arg::with_name("n_blocks")
+            .short("n")
+            .long("n-blocks")
+            .value_name("BLOCKS")
+            .help("Apply an n x n blocking method to speed up the algorithm 
+(default=1, no blocking)")
+            .takes_value(true),
+        Arg::with_name("random_init")
+            .long("random")
+            .help("Use random initialization instead of the (superior) tree algorithm")
+    ]
+}
+
+/// The arguments for PNG and JPEG output
+fn raster_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("dpi")
+            .long("dpi")
+            .value_name("DPI")
+            .help("The resolution of PNG and JPEG output (default=300)")
+            .takes_value(true),
+        Arg::with_name("width")
+            .long("width")
+            .value_name("PIXELS")
+            .help("The width of PNG and JPEG output, instead of using the resolution")
+            .takes_value(true),
+        Arg::with_name("background")
+            .long("background")
+            .value_name("COLOUR")
+            .help("The background colour of PNG, JPEG and PDF output (default=transparent, or white for JPEG)")
+            .takes_value(true),
+        Arg::with_name("thumbnail")
+            .long("thumbnail")
+            .value_name("thumbnail.png")
+            .help("Also write a smaller copy of PNG and JPEG output to this PNG or JPEG file")
+            .takes_value(true),
+        Arg::with_name("thumbnail_scale")
+            .long("thumbnail-scale")
+            .value_name("FACTOR")
+            .help("The size of the thumbnail relative to the full image (default=0.4)")
+            .takes_value(true)
+    ]
+}
+
+/// The arguments for PDF output
+fn pdf_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("page_size")
+            .long("page-size")
+            .value_name("a0-a5|letter|legal|tabloid|WIDTHxHEIGHT")
+            .help("The page size of PDF output, by name or in millimetres (default=a1)")
+            .takes_value(true),
+        Arg::with_name("landscape")
+            .long("landscape")
+            .help("Turn the page of PDF output sideways"),
+        Arg::with_name("margin")
+            .long("margin")
+            .value_name("MM")
+            .help("The margin of PDF output in millimetres (default=15)")
+            .takes_value(true),
+        Arg::with_name("bleed")
+            .long("bleed")
+            .value_name("MM")
+            .help("How far the background of PDF output extends beyond the page in millimetres (default=0)")
+            .takes_value(true),
+        Arg::with_name("crop_marks")
+            .long("crop-marks")
+            .help("Draw crop marks at the corners of the page of PDF output"),
+        Arg::with_name("title")
+            .long("title")
+            .value_name("TITLE")
+            .help("The title of PDF and HTML output (default=The Linked Open Data Cloud)")
+            .takes_value(true)
+    ]
+}
+
+/// The arguments for the settings and the identification of domains
+fn settings_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("settings")
+            .short("e")
+            .long("settings")
+            .value_name("settings.json")
+            .help("The JSON file containing the settings for the system")
+            .takes_value(true),
+        Arg::with_name("ident")
+            .long("ident")
+            .value_name("none|neighbour|tags")
+            .help("The algorithm used to identify domain (bubble colours) of unidentified datasets")
+    ]
+}
+
+/// The arguments for merging further data into the data of a cloud
+fn merge_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("merge")
+            .long("merge")
+            .value_name("data.json")
+            .help("Further data files to merge into the data, later files take precedence
+(may be given multiple times)")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1),
+        Arg::with_name("merge_strategy")
+            .long("merge-strategy")
+            .value_name("last|first")
+            .help("How conflicting values of merged datasets are resolved (default=last)")
+            .takes_value(true)
+    ]
+}
+
 fn main() {
     let args = App::new("LOD cloud diagram SVG creator")
         .version("1.0")
@@ -46,184 +229,344 @@ Where:
   well(v, c): Indicates if v is contained within a circle (well) of radius c
 
 And s,r,w are tuning constants")
-        .arg(Arg::with_name("spring")
-             .short("s")
-             .long("spring")
-             .value_name("FORCE")
-             .help("The value of the spring force")
-             .takes_value(true))
-        .arg(Arg::with_name("repulse")
-             .short("r")
-             .long("repulse")
-             .value_name("FORCE")
-             .help("The value of the repulsion force")
-             .takes_value(true))
-        .arg(Arg::with_name("repulse_dist")
-             .short("d")
-             .long("distance")
-             .value_name("PIXELS")
-             .help("The minimal distance between bubbles")
-             .takes_value(true))
-        .arg(Arg::with_name("repulse_rigidity")
-             .long("repulse-rigidity")
-             .value_name("FACTOR")
-             .help("The rigidity of repulsion between bubbles")
-             .takes_value(true))
-        .arg(Arg::with_name("canvas")
-             .short("w")
-             .long("well")
-             .value_name("FORCE")
-             .help("The value of the well boundary force")
-             .takes_value(true))
-        .arg(Arg::with_name("canvas_size")
-             .short("c")
-             .long("canvas")
-             .value_name("PIXELS")
-             .help("The radius of the circle that the bubbles should be contained in")
-             .takes_value(true))
-        .arg(Arg::with_name("canvas_rigidity")
-             .long("canvas-rigidity")
-             .value_name("FACTOR")
-             .help("The rigidity of the well")
-             .takes_value(true))
-        .arg(Arg::with_name("settings")
-             .short("e")
-             .long("settings")
-             .value_name("settings.json")
-             .help("The JSON file containing the settings for the system")
-             .takes_value(true))
         .arg(Arg::with_name("data")
              .index(1)
              .required(true)
              .value_name("data.json")
              .help("The data of the LOD cloud")
              .takes_value(true))
-         .arg(Arg::with_name("output")
+        .arg(Arg::with_name("output")
              .index(2)
              .required(true)
              .value_name("output.svg")
-             .help("The path of the SVG file to write to")
+             .help("The path of the SVG (or PNG, JPEG, PDF, HTML or TikZ) file to write to")
              .takes_value(true))
-        .arg(Arg::with_name("algorithm")
-             .long("algorithm")
-             .value_name("lbfgs|sd")
-             .help("The algorithm used to find the cloud diagram (lbfgs = Limited BFGS, sd = Steepest Descent)")
+        .arg(Arg::with_name("format")
+             .long("format")
+             .value_name("svg|png|jpg|pdf|html|tex")
+             .help("The format of the output (default=by the extension of the output file)")
              .takes_value(true))
-        .arg(Arg::with_name("max_iters")
-             .short("i")
-             .long("max-iters")
-             .value_name("ITERATIONS")
-             .help("The maximum number of iterations to perform (default=10000)")
-             .takes_value(true))
-        .arg(Arg::with_name("n_blocks")
-             .short("n")
-             .long("n-blocks")
-             .value_name("BLOCKS")
-             .help("Apply an n x n blocking method to speed up the algorithm 
-(default=1, no blocking)")
-             .takes_value(true))
-        .arg(Arg::with_name("ident")
-             .long("ident")
-             .value_name("none|neighbour|tags")
-             .help("The algorithm used to identify domain (bubble colours) of unidentified datasets"))
-        .arg(Arg::with_name("random_init")
-             .long("random")
-             .help("Use random initialization instead of the (superior) tree algorithm"))
+        .args(&raster_args())
+        .args(&pdf_args())
+        .args(&layout_args())
+        .args(&settings_args())
+        .args(&merge_args())
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(SubCommand::with_name("check")
+             .about("Check the availability of the downloads, examples and SPARQL 
+endpoints of all datasets, updating their status and printing a summary")
+             .arg(Arg::with_name("data")
+                  .index(1)
+                  .required(true)
+                  .value_name("data.json")
+                  .help("The data of the LOD cloud")
+                  .takes_value(true))
+             .arg(Arg::with_name("output")
+                  .index(2)
+                  .value_name("output.json")
+                  .help("The path to write the updated data to (default=standard output)")
+                  .takes_value(true))
+             .arg(Arg::with_name("in_place")
+                  .short("i")
+                  .long("in-place")
+                  .conflicts_with("output")
+                  .help("Overwrite the data with the updated data"))
+             .arg(Arg::with_name("timeout")
+                  .short("t")
+                  .long("timeout")
+                  .value_name("SECONDS")
+                  .help("The time to wait for each request (default=30)")
+                  .takes_value(true))
+             .arg(Arg::with_name("concurrency")
+                  .short("j")
+                  .long("concurrency")
+                  .value_name("REQUESTS")
+                  .help("The number of requests to make in parallel (default=16)")
+                  .takes_value(true)))
+        .subcommand(SubCommand::with_name("ipfs")
+             .about("Annotate the downloads of all datasets with their mirrors on IPFS")
+             .arg(Arg::with_name("data")
+                  .index(1)
+                  .required(true)
+                  .value_name("data.json")
+                  .help("The data of the LOD cloud")
+                  .takes_value(true))
+             .arg(Arg::with_name("output")
+                  .index(2)
+                  .value_name("output.json")
+                  .help("The path to write the updated data to (default=overwrite the data)")
+                  .takes_value(true))
+             .arg(Arg::with_name("hashes")
+                  .long("hashes")
+                  .value_name("ipfs-hashes.csv")
+                  .help("The CSV file mapping IPFS hashes to download URLs (default=ipfs-hashes.csv)")
+                  .takes_value(true))
+             .arg(Arg::with_name("mirrored_only")
+                  .long("mirrored-only")
+                  .help("Only write the datasets that have a mirrored download")))
+        .subcommand(SubCommand::with_name("render")
+             .about("Render an SVG cloud to PNG or JPEG")
+             .arg(Arg::with_name("input")
+                  .index(1)
+                  .required(true)
+                  .value_name("cloud.svg")
+                  .help("The SVG file of the cloud")
+                  .takes_value(true))
+             .arg(Arg::with_name("output")
+                  .index(2)
+                  .required(true)
+                  .value_name("cloud.png")
+                  .help("The PNG or JPEG file to write to")
+                  .takes_value(true))
+             .args(&raster_args()))
+        .subcommand(SubCommand::with_name("stats")
+             .about("Print statistics about the datasets and the graph of the cloud")
+             .arg(Arg::with_name("data")
+                  .index(1)
+                  .required(true)
+                  .value_name("data.json")
+                  .help("The data of the LOD cloud")
+                  .takes_value(true))
+             .args(&settings_args())
+             .args(&merge_args())
+             .arg(Arg::with_name("format")
+                  .long("format")
+                  .value_name("markdown|json")
+                  .help("The format of the statistics (default=markdown)")
+                  .takes_value(true))
+             .arg(Arg::with_name("top")
+                  .long("top")
+                  .value_name("DATASETS")
+                  .help("The number of most linked datasets to report (default=10)")
+                  .takes_value(true)))
+        .subcommand(SubCommand::with_name("diff")
+             .about("Report the changes between two versions of the data, optionally
+drawing a cloud of both versions with the changes highlighted")
+             .arg(Arg::with_name("old")
+                  .index(1)
+                  .required(true)
+                  .value_name("old.json")
+                  .help("The data of the earlier version of the LOD cloud")
+                  .takes_value(true))
+             .arg(Arg::with_name("new")
+                  .index(2)
+                  .required(true)
+                  .value_name("new.json")
+                  .help("The data of the later version of the LOD cloud")
+                  .takes_value(true))
+             .arg(Arg::with_name("format")
+                  .long("format")
+                  .value_name("markdown|json")
+                  .help("The format of the changes (default=markdown)")
+                  .takes_value(true))
+             .arg(Arg::with_name("svg")
+                  .long("svg")
+                  .value_name("diff.svg")
+                  .help("Draw the cloud with the changes highlighted to this SVG (or PNG or JPEG) file")
+                  .takes_value(true))
+             .args(&settings_args())
+             .args(&layout_args()))
         .get_matches();
 
-    match do_main(args) {
-        Err(e) => {
-            eprintln!("{}", e);
-            exit(-1)
-        },
-        Ok(_) => {}
+    let result = match args.subcommand() {
+        ("check", Some(sub_args)) => do_check(sub_args),
+        ("ipfs", Some(sub_args)) => do_ipfs(sub_args),
+        ("render", Some(sub_args)) => do_render(sub_args),
+        ("stats", Some(sub_args)) => do_stats(sub_args),
+        ("diff", Some(sub_args)) => do_diff(sub_args),
+        _ => do_main(args)
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        exit(-1)
     }
 }
 
 fn do_main(args : ArgMatches) -> Result<(),&'static str> {
 
-    let mut model : graph::Model = Default::default();
+    let mut settings = load_settings(&args)?;
 
-    model.spring = args.value_of("spring")
-        .map(|s| { s.parse::<f64>().expect("Spring force not a decimal") })
-        .unwrap_or(0.01);
+    let mut data = load_cloud_data(&args)?;
 
-    model.repulse = args.value_of("repulse")
-        .map(|s| { s.parse::<f64>().expect("Repulsion force not a decimal") })
-        .unwrap_or(10.0);
+    identify(&args, &mut data);
 
-    model.repulse_dist = args.value_of("repulse_dist")
-        .map(|s| { s.parse::<f64>().expect("Distance of bubbles is not a decimal") })
-        .unwrap_or(50.0);
+    let graph = graph::build_graph(&data, &settings);
 
-    model.repulse_rigidity = args.value_of("repulse_rigidity")
-        .map(|s| { s.parse::<f64>().expect("Repulsion rigidity is not a decimal") })
-        .unwrap_or(1.0);
+    eprintln!("{} nodes in graph", graph.n);
 
-    model.canvas = args.value_of("centre")
-        .map(|s| { s.parse::<f64>().expect("Well force not a decimal") })
-        .unwrap_or(1.0);
+    let communities = detect_communities(&mut data, &graph, &mut settings);
 
-    model.canvas_rigidity = args.value_of("canvas_rigidity")
-        .map(|s| { s.parse::<f64>().expect("Canvas rigidity is not a decimal") })
-        .unwrap_or(1.0);
+    let (mut gm, mut x_star) = layout(&args, graph, settings, communities.as_deref());
 
-    model.n_blocks = args.value_of("n_blocks")
-        .map(|s| { s.parse::<usize>().expect("N Blocks not a positive integer") })
-        .unwrap_or(1);
+    add_unlinked(&data, &mut gm, &mut x_star);
 
-    model.canvas_size = args.value_of("canvas_size")
-        .map(|s| { s.parse::<f64>().expect("Canvas size is not a decimal") })
-        .unwrap_or(-1.0); // then we set this later
+    write_cloud(&args, args.value_of("format"), &gm, &x_star, &data, &Default::default(),
+                args.value_of("output").expect("Out file not given"))
+}
 
-    let algorithm = match args.value_of("algorithm") {
-        Some("lbfgs") => "lbfgs",
-        Some("sd") => "sd",
-        Some(a) => panic!("{} is not a supported algorithm", a),
-        None => "lbfgs"
-    };
+/// The format of an output file given by its extension
+fn output_format(out_file : &str) -> &'static str {
+    let path = std::path::Path::new(out_file);
+    if raster::is_jpeg(path) {
+        "jpg"
+    } else if path.extension().map(|e| e.eq_ignore_ascii_case("png")).unwrap_or(false) {
+        "png"
+    } else if path.extension().map(|e| e.eq_ignore_ascii_case("pdf")).unwrap_or(false) {
+        "pdf"
+    } else if path.extension().map(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm")).unwrap_or(false) {
+        "html"
+    } else if path.extension().map(|e| e.eq_ignore_ascii_case("tex")).unwrap_or(false) {
+        "tex"
+    } else {
+        "svg"
+    }
+}
 
-    let ident_algorithm = match args.value_of("ident") {
-        Some("none") => "none",
-        Some("tags") => "tags",
-        Some("neighbour") => "neighbour",
-        Some("neighbor") => "neighbour", // For Americans
-        Some(a) => panic!("{} is not a supported identification algorithm", a),
-        None => "none"
+/// Write the cloud as SVG, PNG, JPEG, PDF, HTML or TikZ, according to `format` or else the 
+/// extension of `out_file`
+fn write_cloud(args : &ArgMatches, format : Option<&str>, gm : &GraphModel, loc : &Vec<f64>, 
+               data : &HashMap<String, Dataset>, highlights : &scene::Highlights,
+               out_file : &str) -> Result<(), &'static str> {
+    let format = format.unwrap_or_else(|| output_format(out_file));
+    let renderer : Box<dyn scene::Renderer> = match format {
+        "svg" => Box::new(svg_renderer(&gm.settings)?),
+        "png" | "jpg" | "jpeg" => Box::new(raster::RasterRenderer {
+            svg: svg_renderer(&gm.settings)?,
+            options: raster_options(args),
+            jpeg: format != "png"
+        }),
+        "pdf" => Box::new(pdf::PdfRenderer { options: pdf_options(args) }),
+        "html" => Box::new(html::HtmlRenderer {
+            title: args.value_of("title").unwrap_or("The Linked Open Data Cloud").to_string()
+        }),
+        "tex" | "tikz" => Box::new(tikz::TikzRenderer),
+        f => panic!("{} is not a supported format", f)
     };
+    let scene = scene::Scene::new(&gm.graph, loc, data, gm.model.canvas_size, &gm.settings,
+                                  highlights);
+    renderer.write_file(&scene, std::path::Path::new(out_file)).map_err(|e| {
+        eprintln!("{}", e);
+        "Could not write graph"
+    })
+}
 
-    let max_iters = args.value_of("max_iters")
-        .map(|s| { s.parse::<usize>().expect("Iterations is not an integer") })
-        .unwrap_or(10000);
-
-    let settings_filename = args.value_of("settings").unwrap_or("clouds/lod-cloud-settings.json");
-
-    let settings_file = File::open(settings_filename).map_err(|_| "Settings file does not exist (specify with -e)")?;
+/// The renderer of SVG output with the theme and stylesheet of the settings
+fn svg_renderer(settings : &Settings) -> Result<svg::SvgRenderer, &'static str> {
+    let stylesheet = match settings.stylesheet {
+        Some(ref path) if settings.link_stylesheet.unwrap_or(false) => {
+            Some(svg::Stylesheet::Linked(path.clone()))
+        },
+        Some(ref path) => {
+            Some(svg::Stylesheet::Embedded(std::fs::read_to_string(path).map_err(|e| {
+                eprintln!("{}: {}", path, e);
+                "Could not read stylesheet"
+            })?))
+        },
+        None => None
+    };
+    Ok(svg::SvgRenderer {
+        theme: settings.theme.unwrap_or_default(),
+        stylesheet
+    })
+}
 
-    let settings : Settings = serde_json::from_reader(settings_file).map_err(|e| {
-        eprintln!("{:?}", e);
-        "Settings file is not valid JSON"
-    })?;
-    
-    let data_filename = args.value_of("data").expect("Data not found (should not be reachable... this is a bug)");
+/// The options for PNG and JPEG output given by the output arguments
+fn raster_options(args : &ArgMatches) -> raster::RasterOptions {
+    let defaults = raster::RasterOptions::default();
+    raster::RasterOptions {
+        dpi: args.value_of("dpi")
+            .map(|s| { s.parse::<f64>().expect("DPI is not a decimal") })
+            .unwrap_or(defaults.dpi),
+        width: args.value_of("width")
+            .map(|s| { s.parse::<u32>().expect("Width is not a positive integer") }),
+        background: args.value_of("background").map(|s| s.to_string()),
+        thumbnail: args.value_of("thumbnail").map(|s| s.to_string()),
+        thumbnail_scale: args.value_of("thumbnail_scale")
+            .map(|s| { s.parse::<f64>().expect("Thumbnail scale is not a decimal") })
+            .unwrap_or(defaults.thumbnail_scale)
+    }
+}
 
-    let data_file = File::open(data_filename).map_err(|_| "Data file does not exist")?;
+/// The options for PDF output given by the output arguments
+fn pdf_options(args : &ArgMatches) -> pdf::PdfOptions {
+    let defaults = pdf::PdfOptions::default();
+    let (width, height) = args.value_of("page_size")
+        .map(|s| pdf::page_size(s).unwrap_or_else(|e| panic!("{}", e)))
+        .unwrap_or(defaults.page_size);
+    pdf::PdfOptions {
+        page_size: if args.is_present("landscape") { (height, width) } else { (width, height) },
+        margin: args.value_of("margin")
+            .map(|s| { s.parse::<f64>().expect("Margin is not a decimal") })
+            .unwrap_or(defaults.margin),
+        bleed: args.value_of("bleed")
+            .map(|s| { s.parse::<f64>().expect("Bleed is not a decimal") })
+            .unwrap_or(defaults.bleed),
+        crop_marks: args.is_present("crop_marks"),
+        title: args.value_of("title").map(|s| s.to_string()).unwrap_or(defaults.title),
+        background: args.value_of("background").map(|s| s.to_string())
+    }
+}
 
-    let mut data : HashMap<String,Dataset> = serde_json::from_reader(data_file).map_err(|e| {
-        eprintln!("{:?}", e);
-        "Data contains a JSON error"
-    })?;
+/// If enabled in the settings, find the communities of the graph and use them
+/// as the domains of the datasets and the legend
+fn detect_communities(data : &mut HashMap<String, Dataset>, graph : &graph::Graph,
+                      settings : &mut Settings) -> Option<Vec<usize>> {
+    if settings.communities.unwrap_or(false) {
+        let communities = community::louvain(graph);
+        eprintln!("{} communities found (modularity {:.3})", 
+                  community::count(&communities), 
+                  community::modularity(graph, &communities));
+        community::assign_domains(data, graph, &communities);
+        settings.legend = community::legend(&communities);
+        Some(communities)
+    } else {
+        None
+    }
+}
 
-    match ident_algorithm {
-        "none" => {},
-        "neighbour" => ident::domain_by_most_neighbours(&mut data),
-        "tags" => ident::domain_by_keywords(&mut data),
-        _ => panic!("Unreachable")
+/// Find the locations of the vertices of the graph by minimising the cost
+/// function, using the algorithm and parameters given by the layout arguments.
+/// If `communities` are given, each community starts as a separate tree.
+fn layout(args : &ArgMatches, graph : graph::Graph, settings : Settings,
+          communities : Option<&[usize]>) -> (GraphModel, Vec<f64>) {
+    let mut model = graph::Model {
+        spring : args.value_of("spring")
+            .map(|s| { s.parse::<f64>().expect("Spring force not a decimal") })
+            .unwrap_or(0.01),
+        repulse : args.value_of("repulse")
+            .map(|s| { s.parse::<f64>().expect("Repulsion force not a decimal") })
+            .unwrap_or(10.0),
+        repulse_dist : args.value_of("repulse_dist")
+            .map(|s| { s.parse::<f64>().expect("Distance of bubbles is not a decimal") })
+            .unwrap_or(50.0),
+        repulse_rigidity : args.value_of("repulse_rigidity")
+            .map(|s| { s.parse::<f64>().expect("Repulsion rigidity is not a decimal") })
+            .unwrap_or(1.0),
+        canvas : args.value_of("centre")
+            .map(|s| { s.parse::<f64>().expect("Well force not a decimal") })
+            .unwrap_or(1.0),
+        canvas_rigidity : args.value_of("canvas_rigidity")
+            .map(|s| { s.parse::<f64>().expect("Canvas rigidity is not a decimal") })
+            .unwrap_or(1.0),
+        n_blocks : args.value_of("n_blocks")
+            .map(|s| { s.parse::<usize>().expect("N Blocks not a positive integer") })
+            .unwrap_or(1),
+        canvas_size : args.value_of("canvas_size")
+            .map(|s| { s.parse::<f64>().expect("Canvas size is not a decimal") })
+            .unwrap_or(-1.0), // then we set this later
     };
 
-    let graph = graph::build_graph(&data, &settings);
+    let algorithm = match args.value_of("algorithm") {
+        Some("lbfgs") => "lbfgs",
+        Some("sd") => "sd",
+        Some(a) => panic!("{} is not a supported algorithm", a),
+        None => "lbfgs"
+    };
 
-    eprintln!("{} nodes in graph", graph.n);
+    let max_iters = args.value_of("max_iters")
+        .map(|s| { s.parse::<usize>().expect("Iterations is not an integer") })
+        .unwrap_or(10000);
 
     if model.canvas_size <= 0.0 {
         model.canvas_size = model.repulse_dist * (2.5 + 0.5 * (graph.n as f64).sqrt());
@@ -238,6 +581,10 @@ fn do_main(args : ArgMatches) -> Result<(),&'static str> {
             rng.gen_range((-5.0 * model.canvas_size)..(5.0 * model.canvas_size))
         }).collect(),
         &settings.fixed_points)
+    } else if let Some(communities) = communities {
+        graph.set_fixed_points(
+        tree::community_layout(&graph, communities, model.repulse_dist * 5.0),
+        &settings.fixed_points)
     } else {
         graph.set_fixed_points(
         tree::build_tree(&graph, model.repulse_dist * 5.0),
@@ -291,19 +638,273 @@ fn do_main(args : ArgMatches) -> Result<(),&'static str> {
 //        println!("");
 //    }
 
-    svg::write_graph(&gm.graph, &x_star, &data, gm.model.canvas_size, &gm.settings,
-                     args.value_of("output").expect("Out file not given")).expect("Could not write graph");
+    (gm, x_star)
+}
+
+/// Add the datasets that have no links to the graph, placing them around or
+/// below the cloud as given by the `unlinked` setting
+fn add_unlinked(data : &HashMap<String, Dataset>, gm : &mut GraphModel, x : &mut Vec<f64>) {
+    let unlinked = graph::unlinked_selected(data, &gm.settings);
+    let loc = match gm.settings.unlinked {
+        Some(UnlinkedLayout::Ring) => tree::ring_layout(x, unlinked.len(), gm.model.repulse_dist),
+        Some(UnlinkedLayout::Grid) => tree::grid_layout(x, unlinked.len(), gm.model.repulse_dist),
+        None => {
+            if !unlinked.is_empty() {
+                eprintln!("{} dataset(s) without links are not shown (set `unlinked` in the settings to include them)",
+                          unlinked.len());
+            }
+            return;
+        }
+    };
+    eprintln!("{} dataset(s) without links are included", unlinked.len());
+    for dataset in unlinked.iter() {
+        gm.graph.add_vertex(dataset);
+    }
+    x.extend(loc);
+}
+
+fn do_check(args : &ArgMatches) -> Result<(), &'static str> {
+    let options = check::CheckOptions {
+        timeout: std::time::Duration::from_secs_f64(args.value_of("timeout")
+            .map(|s| { s.parse::<f64>().expect("Timeout is not a decimal") })
+            .unwrap_or(30.0)),
+        concurrency: args.value_of("concurrency")
+            .map(|s| { s.parse::<usize>().expect("Concurrency is not a positive integer") })
+            .unwrap_or(16)
+    };
+
+    let data_filename = args.value_of("data").expect("Data not found (should not be reachable... this is a bug)");
+
+    let mut data = load_json(data_filename)?;
+
+    check::check_data(&mut data, &options);
+
+    let out_filename = if args.is_present("in_place") {
+        Some(data_filename)
+    } else {
+        args.value_of("output")
+    };
+    match out_filename {
+        Some(out_filename) => {
+            write_json(out_filename, &data)?;
+            print!("{}", check::summarise(&data));
+        },
+        None => {
+            // The summary goes to standard error to keep the data readable
+            serde_json::to_writer_pretty(std::io::stdout().lock(), &data)
+                .map_err(|_| "Could not write data")?;
+            println!();
+            eprint!("{}", check::summarise(&data));
+        }
+    }
+
+    Ok(())
+}
+
+fn do_ipfs(args : &ArgMatches) -> Result<(), &'static str> {
+    let hashes_file = File::open(args.value_of("hashes").unwrap_or("ipfs-hashes.csv"))
+        .map_err(|_| "IPFS hashes file does not exist (specify with --hashes)")?;
+    let hashes = ipfs::read_hashes(hashes_file).map_err(|_| "Could not read IPFS hashes file")?;
+
+    let data_filename = args.value_of("data").expect("Data not found (should not be reachable... this is a bug)");
+
+    let mut data = load_json(data_filename)?;
+
+    let annotated = ipfs::annotate(&mut data, &hashes);
+    eprintln!("{} download(s) mirrored on IPFS", annotated);
+
+    if args.is_present("mirrored_only") {
+        ipfs::retain_mirrored(&mut data);
+        eprintln!("{} dataset(s) with a mirrored download", data.len());
+    }
+
+    write_json(args.value_of("output").unwrap_or(data_filename), &data)
+}
+
+fn do_render(args : &ArgMatches) -> Result<(), &'static str> {
+    let input = args.value_of("input").expect("Input not found (should not be reachable... this is a bug)");
+    let output = args.value_of("output").expect("Output not found (should not be reachable... this is a bug)");
+
+    let image = std::fs::read(input).map_err(|_| "Could not read SVG file")?;
+
+    raster::write_raster(&image, &raster_options(args), output).map_err(|e| {
+        eprintln!("{}", e);
+        "Could not write image"
+    })
+}
+
+fn do_stats(args : &ArgMatches) -> Result<(), &'static str> {
+    let json = match args.value_of("format") {
+        Some("markdown") => false,
+        Some("json") => true,
+        Some(f) => panic!("{} is not a supported format", f),
+        None => false
+    };
+
+    let top = args.value_of("top")
+        .map(|s| { s.parse::<usize>().expect("Top is not an integer") })
+        .unwrap_or(10);
+
+    let settings = load_settings(args)?;
+
+    let mut data = load_cloud_data(args)?;
+
+    let unidentified = data.values().filter(|d| d.domain.is_empty()).count();
+    identify(args, &mut data);
+    let identified = unidentified - data.values().filter(|d| d.domain.is_empty()).count();
+
+    let graph = graph::build_graph(&data, &settings);
+
+    let statistics = stats::statistics(&data, &graph, identified, top);
+
+    if json {
+        serde_json::to_writer_pretty(std::io::stdout(), &statistics)
+            .map_err(|_| "Could not write statistics")?;
+        println!();
+    } else {
+        print!("{}", statistics);
+    }
 
     Ok(())
 }
 
+fn do_diff(args : &ArgMatches) -> Result<(), &'static str> {
+    let json = match args.value_of("format") {
+        Some("markdown") => false,
+        Some("json") => true,
+        Some(f) => panic!("{} is not a supported format", f),
+        None => false
+    };
+
+    let mut old = load_data(args.value_of("old").expect("Old data not found (should not be reachable... this is a bug)"))?;
+    let mut new = load_data(args.value_of("new").expect("New data not found (should not be reachable... this is a bug)"))?;
+
+    identify(args, &mut old);
+    identify(args, &mut new);
+
+    let changes = diff::diff(&old, &new);
+
+    if json {
+        serde_json::to_writer_pretty(std::io::stdout(), &changes)
+            .map_err(|_| "Could not write changes")?;
+        println!();
+    } else {
+        print!("{}", changes);
+    }
+
+    if let Some(svg_filename) = args.value_of("svg") {
+        let mut settings = load_settings(args)?;
+
+        let mut data = diff::union(&old, &new);
+
+        let graph = graph::build_graph(&data, &settings);
+
+        eprintln!("{} nodes in graph", graph.n);
+
+        let communities = detect_communities(&mut data, &graph, &mut settings);
+
+        let (mut gm, mut x_star) = layout(args, graph, settings, communities.as_deref());
+
+        add_unlinked(&data, &mut gm, &mut x_star);
+
+        write_cloud(args, None, &gm, &x_star, &data, &changes.highlights(), svg_filename)?;
+    }
+
+    Ok(())
+}
+
+/// Load the settings given by `--settings`
+fn load_settings(args : &ArgMatches) -> Result<Settings, &'static str> {
+    let settings_filename = args.value_of("settings").unwrap_or("clouds/lod-cloud-settings.json");
+
+    let settings_file = File::open(settings_filename).map_err(|_| "Settings file does not exist (specify with -e)")?;
+
+    serde_json::from_reader(settings_file).map_err(|e| {
+        eprintln!("{}", e);
+        "Settings file is not valid"
+    })
+}
+
+/// Load the data and merge in any data given by `--merge`
+fn load_cloud_data(args : &ArgMatches) -> Result<HashMap<String, Dataset>, &'static str> {
+    let merge_strategy = match args.value_of("merge_strategy") {
+        Some("first") => MergeStrategy::First,
+        Some("last") => MergeStrategy::Last,
+        Some(s) => panic!("{} is not a supported merge strategy", s),
+        None => MergeStrategy::Last
+    };
+
+    let data_filename = args.value_of("data").expect("Data not found (should not be reachable... this is a bug)");
+
+    let mut data = load_data(data_filename)?;
+
+    for merge_filename in args.values_of("merge").into_iter().flatten() {
+        let overlaps = data::merge(&mut data, load_data(merge_filename)?, merge_strategy);
+        eprintln!("Merged {}: {} dataset(s) overlap", merge_filename, overlaps.len());
+        for overlap in overlaps.iter() {
+            eprintln!("  {}", overlap);
+        }
+    }
+
+    for u in data::unparseable_values(&data) {
+        eprintln!("Could not parse {} of {} (\"{}\"), treating as zero",
+                  u.field, u.dataset, u.text);
+    }
+
+    Ok(data)
+}
+
+/// Identify the domains of unidentified datasets by the algorithm given by `--ident`
+fn identify(args : &ArgMatches, data : &mut HashMap<String, Dataset>) {
+    let ident_algorithm = match args.value_of("ident") {
+        Some("none") => "none",
+        Some("tags") => "tags",
+        Some("neighbour") => "neighbour",
+        Some("neighbor") => "neighbour", // For Americans
+        Some(a) => panic!("{} is not a supported identification algorithm", a),
+        None => "none"
+    };
+
+    match ident_algorithm {
+        "none" => {},
+        "neighbour" => ident::domain_by_most_neighbours(data),
+        "tags" => ident::domain_by_keywords(data),
+        _ => panic!("Unreachable")
+    };
+}
+
+/// Load the data as plain JSON, so that it may be written back without loss
+fn load_json(data_filename : &str) -> Result<serde_json::Map<String, serde_json::Value>, &'static str> {
+    let data_file = File::open(data_filename).map_err(|_| "Data file does not exist")?;
+
+    serde_json::from_reader(data_file).map_err(|e| {
+        eprintln!("{:?}", e);
+        "Data contains a JSON error"
+    })
+}
+
+fn write_json(out_filename : &str, data : &serde_json::Map<String, serde_json::Value>) -> Result<(), &'static str> {
+    let out_file = File::create(out_filename).map_err(|_| "Could not create output file")?;
+    serde_json::to_writer_pretty(BufWriter::new(out_file), data)
+        .map_err(|_| "Could not write data")
+}
+
+fn load_data(data_filename : &str) -> Result<HashMap<String, Dataset>, &'static str> {
+    let data_file = File::open(data_filename).map_err(|_| "Data file does not exist")?;
+
+    serde_json::from_reader(data_file).map_err(|e| {
+        eprintln!("{:?}", e);
+        "Data contains a JSON error"
+    })
+}
+
 struct GraphModel {
     graph : graph::Graph,
     model : graph::Model,
     settings : Settings
 }
 
-impl<'a> CostFunction for &'a GraphModel {
+impl CostFunction for &GraphModel {
     type Param = Vec<f64>;
     type Output = f64;
 
@@ -312,7 +913,7 @@ impl<'a> CostFunction for &'a GraphModel {
     }
 }
 
-impl<'a> Gradient for &'a GraphModel {
+impl Gradient for &GraphModel {
     type Param = Vec<f64>;
     type Gradient = Vec<f64>;
 
@@ -0,0 +1,767 @@
+//! Methods for outputting graphs as PDF posters for printing, with embedded
+//! fonts, a title block and the legend, rights text and logo placed below the
+//! cloud
+use crate::data::Availability;
+use crate::raster;
+use crate::label::LINE_SPACING;
+use crate::scene::{self, Logo, Renderer, Scene, ARROW_LENGTH, LINE_HEIGHT};
+use htmlescape::encode_attribute;
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use pdf_writer::types::{CidFontType, FontFlags, SystemInfo, UnicodeCmap};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use resvg::tiny_skia::Pixmap;
+use resvg::usvg::fontdb::{Style, Weight};
+use resvg::usvg::Tree;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use ttf_parser::{Face, GlyphId};
+
+/// Points (the unit of PDF) per millimetre
+const PT_PER_MM : f64 = 72.0 / 25.4;
+/// The length of the crop marks in millimetres
+const MARK_LENGTH : f64 = 8.0;
+/// The resolution the logo is drawn at
+const LOGO_DPI : f64 = 600.0;
+/// The level of compression of the streams
+const COMPRESSION : u8 = 6;
+/// The character collection of the embedded font, whose characters are
+/// simply the glyphs of the font
+const IDENTITY : SystemInfo<'static> = SystemInfo {
+    registry: Str(b"Adobe"),
+    ordering: Str(b"Identity"),
+    supplement: 0
+};
+
+/// The options for printing a cloud
+#[derive(Clone,Debug,PartialEq)]
+pub struct PdfOptions {
+    /// The width and height of the (trimmed) page in millimetres
+    pub page_size : (f64, f64),
+    /// The space left blank at the edges of the page in millimetres
+    pub margin : f64,
+    /// How far the background extends beyond the edges of the page in
+    /// millimetres, to allow for inaccurate cutting
+    pub bleed : f64,
+    /// Whether to draw marks at the corners of the page to cut along
+    pub crop_marks : bool,
+    /// The title shown above the cloud
+    pub title : String,
+    /// The background colour, unprinted if not given
+    pub background : Option<String>
+}
+
+impl Default for PdfOptions {
+    fn default() -> PdfOptions {
+        PdfOptions {
+            page_size: (594.0, 841.0),
+            margin: 15.0,
+            bleed: 0.0,
+            crop_marks: false,
+            title: "The Linked Open Data Cloud".to_string(),
+            background: None
+        }
+    }
+}
+
+/// The width and height of a page in millimetres, by the name of a paper size
+/// (e.g., "a1" or "letter") or as "WIDTHxHEIGHT" in millimetres
+pub fn page_size(name : &str) -> Result<(f64, f64), String> {
+    match name.to_lowercase().as_str() {
+        "a0" => Ok((841.0, 1189.0)),
+        "a1" => Ok((594.0, 841.0)),
+        "a2" => Ok((420.0, 594.0)),
+        "a3" => Ok((297.0, 420.0)),
+        "a4" => Ok((210.0, 297.0)),
+        "a5" => Ok((148.0, 210.0)),
+        "letter" => Ok((215.9, 279.4)),
+        "legal" => Ok((215.9, 355.6)),
+        "tabloid" => Ok((279.4, 431.8)),
+        size => {
+            let mut parts = size.splitn(2, 'x').map(|p| p.trim().parse::<f64>());
+            match (parts.next(), parts.next()) {
+                (Some(Ok(w)), Some(Ok(h))) if w > 0.0 && h > 0.0 => Ok((w, h)),
+                _ => Err(format!("{} is not a page size (a0-a5, letter, legal, tabloid or WIDTHxHEIGHT in mm)", name))
+            }
+        }
+    }
+}
+
+/// How a line or the outline of a bubble is drawn, following the CSS of the
+/// SVG output
+#[derive(Clone,Copy,Debug,PartialEq)]
+struct Stroke {
+    colour : &'static str,
+    width : f64,
+    dash : Option<(f64, f64)>,
+    /// The graphics state setting the opacity
+    opacity : &'static [u8]
+}
+
+/// The graphics states of the page and their fill and stroke opacities
+const OPACITIES : [(&[u8], f32, f32); 9] = [
+    (b"Opaque", 1.0, 1.0),
+    (b"Bubble", 0.8, 1.0),
+    (b"Link", 1.0, 0.7),
+    (b"Removed", 0.15, 0.4),
+    (b"Weight1", 1.0, 0.2),
+    (b"Weight2", 1.0, 0.4),
+    (b"Weight3", 1.0, 0.6),
+    (b"Weight4", 1.0, 0.8),
+    (b"Weight5", 1.0, 1.0)
+];
+
+/// The graphics state nearest to the opacity of a weighted link
+fn weight_opacity(opacity : f64) -> &'static [u8] {
+    const STATES : [&[u8]; 5] = [b"Weight1", b"Weight2", b"Weight3", b"Weight4", b"Weight5"];
+    STATES[((opacity * 5.0).round() as usize).clamp(1, 5) - 1]
+}
+
+fn link_stroke(bidirectional : bool, highlight : Option<&str>) -> Stroke {
+    let mut stroke = Stroke { colour: "#555", width: 1.0, dash: None, opacity: b"Link" };
+    if bidirectional {
+        stroke.colour = "#333";
+        stroke.width = 2.5;
+    }
+    match highlight {
+        Some("link-added") => {
+            stroke.colour = "#1AC21D";
+            stroke.width = 2.0;
+        },
+        Some("link-removed") => {
+            stroke.colour = "#be1b38";
+            stroke.width = 2.0;
+            stroke.dash = Some((6.0, 4.0));
+        },
+        Some("link-changed") => {
+            stroke.colour = "#f6a000";
+            stroke.width = 2.0;
+        },
+        _ => {}
+    }
+    stroke
+}
+
+/// The stroke of a link with the width and opacity of its weight, if any
+fn weighted(mut stroke : Stroke, weight : &scene::Weight) -> Stroke {
+    if let Some(width) = weight.width {
+        stroke.width = width;
+    }
+    if let Some(opacity) = weight.opacity {
+        stroke.opacity = weight_opacity(opacity);
+    }
+    stroke
+}
+
+fn bubble_stroke(availability : Option<Availability>, highlight : Option<&str>) -> Stroke {
+    let mut stroke = Stroke { colour: "#333", width: 1.5, dash: None, opacity: b"Bubble" };
+    match availability {
+        Some(Availability::Unavailable) => {
+            stroke.colour = "#d00000";
+            stroke.width = 3.0;
+            stroke.dash = Some((6.0, 4.0));
+        },
+        Some(Availability::Unknown) => {
+            stroke.colour = "#999";
+            stroke.dash = Some((2.0, 3.0));
+        },
+        _ => {}
+    }
+    match highlight {
+        Some("added") => {
+            stroke.colour = "#1AC21D";
+            stroke.width = 5.0;
+        },
+        Some("removed") => {
+            stroke.opacity = b"Removed";
+            stroke.dash = Some((4.0, 4.0));
+        },
+        Some("changed") => {
+            stroke.colour = "#f6a000";
+            stroke.width = 4.0;
+        },
+        _ => {}
+    }
+    stroke
+}
+
+/// A TrueType font embedded in the PDF, recording the glyphs used so that
+/// their widths and characters can be written
+struct Font {
+    data : Vec<u8>,
+    index : u32,
+    glyphs : BTreeMap<u16, char>
+}
+
+impl Font {
+    /// Find the first installed fallback font with TrueType outlines
+    fn load() -> Result<Font, String> {
+        let options = raster::font_options();
+        for name in raster::FALLBACK_FONTS.iter() {
+            for face in options.fontdb.faces() {
+                if face.style != Style::Normal || face.weight != Weight::NORMAL ||
+                    !face.families.iter().any(|(family, _)| family == name) {
+                    continue;
+                }
+                let font = options.fontdb.with_face_data(face.id, |data, index| Font {
+                    data: data.to_vec(),
+                    index,
+                    glyphs: BTreeMap::new()
+                });
+                if let Some(font) = font {
+                    // A font collection cannot be embedded as it is
+                    if !font.data.starts_with(b"ttcf") &&
+                        Face::parse(&font.data, font.index).map(|f| f.tables().glyf.is_some()).unwrap_or(false) {
+                        return Ok(font);
+                    }
+                }
+            }
+        }
+        Err(format!("None of the fonts {} is installed as TrueType", raster::FALLBACK_FONTS.join(", ")))
+    }
+
+    fn face(&self) -> Face<'_> {
+        Face::parse(&self.data, self.index).expect("Font was checked when loaded")
+    }
+
+    /// The width of a text in points
+    fn width(&self, text : &str, size : f64) -> f64 {
+        let face = self.face();
+        let units : u32 = text.chars().map(|c| {
+            face.glyph_index(c).and_then(|g| face.glyph_hor_advance(g)).unwrap_or(0) as u32
+        }).sum();
+        units as f64 * size / face.units_per_em() as f64
+    }
+
+    /// Encode a text as the glyphs of the font
+    fn encode(&mut self, text : &str) -> Vec<u8> {
+        let glyphs : Vec<(u16, char)> = {
+            let face = self.face();
+            text.chars().map(|c| (face.glyph_index(c).unwrap_or(GlyphId(0)).0, c)).collect()
+        };
+        let mut encoded = Vec::new();
+        for (glyph, c) in glyphs {
+            self.glyphs.entry(glyph).or_insert(c);
+            encoded.extend_from_slice(&glyph.to_be_bytes());
+        }
+        encoded
+    }
+
+    /// Write the font and the widths and characters of the glyphs used. The
+    /// font is referred to by the first reference and the others are used
+    /// for its parts
+    fn write(&self, pdf : &mut Pdf, refs : [Ref; 5]) {
+        let [font_id, cid_id, descriptor_id, file_id, cmap_id] = refs;
+        let face = self.face();
+        let scale = 1000.0 / face.units_per_em() as f32;
+        let name : String = face.names().into_iter()
+            .find(|n| n.name_id == ttf_parser::name_id::POST_SCRIPT_NAME)
+            .and_then(|n| n.to_string())
+            .unwrap_or_else(|| "Font".to_string())
+            .chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-').collect();
+        let base_font = Name(name.as_bytes());
+
+        pdf.type0_font(font_id)
+            .base_font(base_font)
+            .encoding_predefined(Name(b"Identity-H"))
+            .descendant_font(cid_id)
+            .to_unicode(cmap_id);
+
+        let mut cid = pdf.cid_font(cid_id);
+        cid.subtype(CidFontType::Type2)
+            .base_font(base_font)
+            .system_info(IDENTITY)
+            .font_descriptor(descriptor_id)
+            .cid_to_gid_map_predefined(Name(b"Identity"));
+        let mut widths = cid.widths();
+        for &glyph in self.glyphs.keys() {
+            let advance = face.glyph_hor_advance(GlyphId(glyph)).unwrap_or(0);
+            widths.consecutive(glyph, [advance as f32 * scale]);
+        }
+        widths.finish();
+        cid.finish();
+
+        let bbox = face.global_bounding_box();
+        pdf.font_descriptor(descriptor_id)
+            .name(base_font)
+            .flags(FontFlags::NON_SYMBOLIC)
+            .bbox(Rect::new(bbox.x_min as f32 * scale, bbox.y_min as f32 * scale,
+                            bbox.x_max as f32 * scale, bbox.y_max as f32 * scale))
+            .italic_angle(0.0)
+            .ascent(face.ascender() as f32 * scale)
+            .descent(face.descender() as f32 * scale)
+            .cap_height(face.capital_height().unwrap_or(face.ascender()) as f32 * scale)
+            .stem_v(80.0)
+            .font_file2(file_id);
+
+        let compressed = compress_to_vec_zlib(&self.data, COMPRESSION);
+        pdf.stream(file_id, &compressed)
+            .filter(Filter::FlateDecode)
+            .pair(Name(b"Length1"), self.data.len() as i32);
+
+        let mut cmap = UnicodeCmap::new(Name(b"Custom"), IDENTITY);
+        for (&glyph, &c) in self.glyphs.iter() {
+            cmap.pair(glyph, c);
+        }
+        pdf.stream(cmap_id, &cmap.finish());
+    }
+}
+
+/// Break a text into lines no wider than `width` points, breaking only
+/// between words
+fn wrap(font : &Font, text : &str, size : f64, width : f64) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && font.width(&format!("{} {}", line, word), size) > width {
+            lines.push(line);
+            line = String::new();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// The page being drawn, where coordinates are in points from the top left
+/// corner of the page (including any bleed and crop marks)
+struct Canvas {
+    content : Content,
+    font : Font,
+    height : f64
+}
+
+impl Canvas {
+    fn fill(&mut self, colour : &str) {
+        let (r, g, b) = rgb(colour);
+        self.content.set_fill_rgb(r, g, b);
+    }
+
+    /// Set the stroke, scaling its width and dashes
+    fn stroke(&mut self, stroke : &Stroke, scale : f64) {
+        let (r, g, b) = rgb(stroke.colour);
+        self.content.set_stroke_rgb(r, g, b)
+            .set_line_width((stroke.width * scale).max(0.1) as f32)
+            .set_parameters(Name(stroke.opacity));
+        match stroke.dash {
+            Some((on, off)) => self.content.set_dash_pattern([(on * scale) as f32, (off * scale) as f32], 0.0),
+            None => self.content.set_dash_pattern([], 0.0)
+        };
+    }
+
+    fn line(&mut self, x1 : f64, y1 : f64, x2 : f64, y2 : f64) {
+        self.content.move_to(x1 as f32, (self.height - y1) as f32)
+            .line_to(x2 as f32, (self.height - y2) as f32)
+            .stroke();
+    }
+
+    /// Stroke a path of quadratic curves, each of which starts at the end
+    /// of the one before, as the equivalent cubic Bézier curves
+    fn curves(&mut self, curves : &[(scene::Point, scene::Point, scene::Point)]) {
+        if let Some(&((x, y), _, _)) = curves.first() {
+            self.content.move_to(x as f32, (self.height - y) as f32);
+        }
+        for &((x1, y1), (cx, cy), (x2, y2)) in curves {
+            let c1 = (x1 + 2.0 / 3.0 * (cx - x1), y1 + 2.0 / 3.0 * (cy - y1));
+            let c2 = (x2 + 2.0 / 3.0 * (cx - x2), y2 + 2.0 / 3.0 * (cy - y2));
+            self.content.cubic_to(c1.0 as f32, (self.height - c1.1) as f32,
+                                  c2.0 as f32, (self.height - c2.1) as f32,
+                                  x2 as f32, (self.height - y2) as f32);
+        }
+        self.content.stroke();
+    }
+
+    /// Fill an arrowhead with its tip at `tip`, pointing away from `from`
+    fn arrowhead(&mut self, (x, y) : (f64, f64), from : (f64, f64), length : f64) {
+        let (dx, dy) = (x - from.0, y - from.1);
+        let norm = (dx * dx + dy * dy).sqrt();
+        if norm == 0.0 {
+            return;
+        }
+        let (ux, uy) = (dx / norm * length, dy / norm * length);
+        let (bx, by) = (x - ux, y - uy);
+        self.content.move_to(x as f32, (self.height - y) as f32)
+            .line_to((bx - uy / 2.0) as f32, (self.height - by - ux / 2.0) as f32)
+            .line_to((bx + uy / 2.0) as f32, (self.height - by + ux / 2.0) as f32)
+            .close_path()
+            .fill_nonzero();
+    }
+
+    fn rect(&mut self, x : f64, y : f64, width : f64, height : f64) {
+        self.content.rect(x as f32, (self.height - y - height) as f32, width as f32, height as f32);
+    }
+
+    /// Add a circle to the path, made of four Bézier curves
+    fn circle(&mut self, x : f64, y : f64, r : f64) {
+        let k = 0.5523 * r;
+        let y = self.height - y;
+        let curves = [
+            [(x + r, y + k), (x + k, y + r), (x, y + r)],
+            [(x - k, y + r), (x - r, y + k), (x - r, y)],
+            [(x - r, y - k), (x - k, y - r), (x, y - r)],
+            [(x + k, y - r), (x + r, y - k), (x + r, y)]
+        ];
+        self.content.move_to((x + r) as f32, y as f32);
+        for [(x1, y1), (x2, y2), (x3, y3)] in curves {
+            self.content.cubic_to(x1 as f32, y1 as f32, x2 as f32, y2 as f32, x3 as f32, y3 as f32);
+        }
+        self.content.close_path();
+    }
+
+    /// Write a text starting at `x` with its baseline at `y`
+    fn text(&mut self, x : f64, y : f64, size : f64, text : &str) {
+        let encoded = self.font.encode(text);
+        self.content.begin_text()
+            .set_font(Name(b"F1"), size as f32)
+            .next_line(x as f32, (self.height - y) as f32)
+            .show(Str(&encoded))
+            .end_text();
+    }
+
+    /// Write a text centred on `x`
+    fn centred_text(&mut self, x : f64, y : f64, size : f64, text : &str) {
+        let width = self.font.width(text, size);
+        self.text(x - width / 2.0, y, size, text);
+    }
+}
+
+/// The red, green and blue of a CSS colour (or white if it cannot be read)
+fn rgb(colour : &str) -> (f32, f32, f32) {
+    svgtypes::Color::from_str(colour)
+        .map(|c| (c.red as f32 / 255.0, c.green as f32 / 255.0, c.blue as f32 / 255.0))
+        .unwrap_or((1.0, 1.0, 1.0))
+}
+
+/// Draw the logo to a height of `height` points. Logos that are not local
+/// files are left out
+fn load_logo(logo : &Logo, height : f64) -> Option<Pixmap> {
+    let svg = match *logo {
+        Logo::Image { href : ref link, width } => {
+            let path = match Path::new(link).canonicalize() {
+                Ok(path) if path.is_file() => path,
+                _ => {
+                    eprintln!("The logo {} is not a local file and is left out of the PDF", link);
+                    return None;
+                }
+            };
+            format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\"><image width=\"{}\" height=\"{}\" href=\"{}\"/></svg>",
+                    width, LINE_HEIGHT, width, LINE_HEIGHT,
+                    encode_attribute(&path.display().to_string()))
+        },
+        Logo::Default => include_str!("by.svg").to_string()
+    };
+    let tree = Tree::from_str(&svg, &raster::font_options()).ok()?;
+    let scale = height / 72.0 * LOGO_DPI / tree.size().height() as f64;
+    raster::render(&tree, scale, None).ok()
+}
+
+/// Write the logo as an image with its transparency as a soft mask
+fn write_logo(pdf : &mut Pdf, logo : &Pixmap, image_id : Ref, mask_id : Ref) {
+    let mut colours = Vec::with_capacity(logo.pixels().len() * 3);
+    let mut alpha = Vec::with_capacity(logo.pixels().len());
+    for pixel in logo.pixels() {
+        let c = pixel.demultiply();
+        colours.extend_from_slice(&[c.red(), c.green(), c.blue()]);
+        alpha.push(c.alpha());
+    }
+    let colours = compress_to_vec_zlib(&colours, COMPRESSION);
+    let mut image = pdf.image_xobject(image_id, &colours);
+    image.width(logo.width() as i32)
+        .height(logo.height() as i32)
+        .bits_per_component(8)
+        .s_mask(mask_id)
+        .filter(Filter::FlateDecode);
+    image.color_space().device_rgb();
+    image.finish();
+    let alpha = compress_to_vec_zlib(&alpha, COMPRESSION);
+    let mut mask = pdf.image_xobject(mask_id, &alpha);
+    mask.width(logo.width() as i32)
+        .height(logo.height() as i32)
+        .bits_per_component(8)
+        .filter(Filter::FlateDecode);
+    mask.color_space().device_gray();
+}
+
+/// Writes scenes as PDF posters to be printed
+pub struct PdfRenderer {
+    pub options : PdfOptions
+}
+
+impl Renderer for PdfRenderer {
+    fn render(&self, scene : &Scene, out : &mut dyn Write) -> Result<(), String> {
+        let pdf = write_pdf(scene, &self.options)?;
+        out.write_all(&pdf).map_err(|e| format!("Could not write PDF: {}", e))
+    }
+}
+
+/// Lay out a scene on a page and write it as PDF
+fn write_pdf(scene : &Scene, options : &PdfOptions) -> Result<Vec<u8>, String> {
+    // The page, with the bleed and the crop marks outside it
+    let page_width = options.page_size.0 * PT_PER_MM;
+    let page_height = options.page_size.1 * PT_PER_MM;
+    let bleed = options.bleed * PT_PER_MM;
+    let offset = bleed + if options.crop_marks { MARK_LENGTH * PT_PER_MM } else { 0.0 };
+    let media_width = page_width + 2.0 * offset;
+    let media_height = page_height + 2.0 * offset;
+
+    let margin = options.margin * PT_PER_MM;
+    let left = offset + margin;
+    let top = offset + margin;
+    let width = page_width - 2.0 * margin;
+    let height = page_height - 2.0 * margin;
+    if width <= 0.0 || height <= 0.0 {
+        return Err("The margin is too large for the page".to_string());
+    }
+
+    let mut canvas = Canvas {
+        content: Content::new(),
+        font: Font::load()?,
+        height: media_height
+    };
+    if let Some(ref background) = options.background {
+        canvas.content.set_parameters(Name(b"Opaque"));
+        canvas.fill(background);
+        canvas.rect(offset - bleed, offset - bleed, page_width + 2.0 * bleed, page_height + 2.0 * bleed);
+        canvas.content.fill_nonzero();
+    }
+
+    // The title block
+    let title_size = width.min(height) / 28.0;
+    let text_size = title_size * 0.4;
+    let subtitle_size = title_size * 0.5;
+    let title_y = top + title_size * 0.75;
+    let subtitle_y = title_y + subtitle_size * 1.6;
+    let rule_y = subtitle_y + subtitle_size * 0.8;
+    canvas.content.set_parameters(Name(b"Opaque"));
+    canvas.fill("black");
+    canvas.text(left, title_y, title_size, &options.title);
+    canvas.fill("#555");
+    canvas.text(left, subtitle_y, subtitle_size,
+                &format!("{} datasets and {} links", scene.bubbles.len(), scene.lines.len()));
+    canvas.stroke(&Stroke { colour: "black", width: title_size / 40.0, dash: None, opacity: b"Opaque" }, 1.0);
+    canvas.line(left, rule_y, left + width, rule_y);
+
+    // The legend, as a grid below the cloud
+    let legend = &scene.legend;
+    let swatch = text_size * 1.2;
+    let row_height = swatch * 1.5;
+    let column_width = swatch + text_size * 2.6 + legend.iter()
+        .map(|e| canvas.font.width(&e.title, text_size)).fold(0.0, f64::max);
+    let columns = ((width + text_size * 2.0) / column_width).floor().max(1.0) as usize;
+    let rows = legend.len().div_ceil(columns);
+    let weight_rows = if scene.weight_legend.is_empty() { 0 } else { 1 };
+    let legend_height = if legend.is_empty() && weight_rows == 0 {
+        0.0
+    } else {
+        text_size * 2.4 + (rows + weight_rows) as f64 * row_height
+    };
+
+    // The rights text and logo at the bottom
+    let logo_height = text_size * 3.0;
+    let logo = load_logo(&scene.logo, logo_height);
+    let logo_width = logo.as_ref()
+        .map(|l| logo_height * l.width() as f64 / l.height() as f64).unwrap_or(0.0);
+    let rights_size = text_size * 0.8;
+    let rights = scene.rights_text.as_ref()
+        .map(|rt| wrap(&canvas.font, rt, rights_size, width - logo_width - text_size * 2.0))
+        .unwrap_or_default();
+    let bottom_height = logo_height.max(rights.len() as f64 * rights_size * 1.3);
+    let footer_top = top + height - bottom_height - legend_height - text_size;
+
+    // The cloud fills the space between
+    let area_top = rule_y + text_size;
+    let area_height = footer_top - text_size - area_top;
+    let side = width.min(area_height);
+    if side <= 0.0 {
+        return Err("The page is too small for the cloud".to_string());
+    }
+    let scale = side / scene.size;
+    let x0 = left + (width - side) / 2.0;
+    let y0 = area_top + (area_height - side) / 2.0;
+    let x = |x : f64| x0 + x * scale;
+    let y = |y : f64| y0 + y * scale;
+
+    for line in scene.lines.iter() {
+        canvas.stroke(&weighted(link_stroke(line.is_bidirectional(), line.highlight.as_deref()), &line.weight), scale);
+        let (start, end) = ((x(line.x1), y(line.y1)), (x(line.x2), y(line.y2)));
+        let mut curves : Vec<_> = line.bundle_curves().into_iter()
+            .map(|(s, c, e)| ((x(s.0), y(s.1)), (x(c.0), y(c.1)), (x(e.0), y(e.1))))
+            .collect();
+        if let Some((cx, cy)) = line.control {
+            curves.push((start, (x(cx), y(cy)), end));
+        }
+        if curves.is_empty() {
+            canvas.line(start.0, start.1, end.0, end.1);
+        } else {
+            canvas.curves(&curves);
+        }
+        if scene.arrows {
+            // The arrowheads point along the ends of the curves
+            let from_start = curves.first().map(|c| c.1).unwrap_or(end);
+            let from_end = curves.last().map(|c| c.1).unwrap_or(start);
+            canvas.fill("#555");
+            canvas.arrowhead(end, from_end, ARROW_LENGTH * scale);
+            if line.is_bidirectional() {
+                canvas.arrowhead(start, from_start, ARROW_LENGTH * scale);
+            }
+        }
+    }
+
+    for bubble in scene.bubbles.iter() {
+        canvas.stroke(&bubble_stroke(bubble.availability, bubble.highlight.as_deref()), scale);
+        canvas.fill(&bubble.colour);
+        canvas.circle(x(bubble.x), y(bubble.y), bubble.r * scale);
+        canvas.content.fill_nonzero_and_stroke();
+
+        canvas.fill("black");
+        let spacing = bubble.label_size * LINE_SPACING;
+        let top = bubble.y - (bubble.label.len().max(1) - 1) as f64 * spacing / 2.0;
+        for (n, line) in bubble.label.iter().enumerate() {
+            canvas.centred_text(x(bubble.x), y(top + n as f64 * spacing), bubble.label_size * scale, line);
+        }
+    }
+
+    if legend_height > 0.0 {
+        canvas.content.set_parameters(Name(b"Opaque"));
+        canvas.fill("black");
+        canvas.text(left, footer_top + text_size * 1.2, text_size * 1.2, "Legend");
+        for (n, entry) in legend.iter().enumerate() {
+            let entry_x = left + (n % columns) as f64 * column_width;
+            let entry_y = footer_top + text_size * 2.4 + (n / columns) as f64 * row_height;
+            canvas.fill(&entry.colour);
+            canvas.rect(entry_x, entry_y, swatch, swatch);
+            match entry.availability {
+                Some(availability) => {
+                    canvas.stroke(&bubble_stroke(Some(availability), None), text_size / 12.0);
+                    canvas.content.fill_nonzero_and_stroke();
+                    canvas.content.set_parameters(Name(b"Opaque"));
+                },
+                None => {
+                    canvas.content.fill_nonzero();
+                }
+            }
+            canvas.fill("black");
+            canvas.text(entry_x + swatch + text_size * 0.6, entry_y + swatch * 0.8, text_size, &entry.title);
+        }
+        // The link weights in a row below, drawn as in the cloud
+        let weights_y = footer_top + text_size * 2.4 + rows as f64 * row_height;
+        let mut entry_x = left + canvas.font.width("Links", text_size) + text_size;
+        if weight_rows > 0 {
+            canvas.fill("black");
+            canvas.text(left, weights_y + swatch * 0.8, text_size, "Links");
+        }
+        for entry in scene.weight_legend.iter() {
+            canvas.stroke(&weighted(link_stroke(false, None), &entry.weight), scale);
+            canvas.line(entry_x, weights_y + swatch / 2.0, entry_x + swatch * 2.5, weights_y + swatch / 2.0);
+            canvas.content.set_parameters(Name(b"Opaque"));
+            canvas.fill("black");
+            let value = entry.value.to_string();
+            canvas.text(entry_x + swatch * 3.0, weights_y + swatch * 0.8, text_size, &value);
+            entry_x += swatch * 3.0 + canvas.font.width(&value, text_size) + text_size * 2.0;
+        }
+    }
+
+    let bottom_top = top + height - bottom_height;
+    canvas.fill("#333");
+    for (n, line) in rights.iter().enumerate() {
+        canvas.text(left, bottom_top + rights_size * (1.0 + 1.3 * n as f64), rights_size, line);
+    }
+    if logo.is_some() {
+        canvas.content.save_state()
+            .transform([logo_width as f32, 0.0, 0.0, logo_height as f32,
+                        (left + width - logo_width) as f32, (media_height - top - height) as f32])
+            .x_object(Name(b"Logo"))
+            .restore_state();
+    }
+
+    if options.crop_marks {
+        canvas.stroke(&Stroke { colour: "black", width: 0.25, dash: None, opacity: b"Opaque" }, 1.0);
+        for &corner_x in [offset, offset + page_width].iter() {
+            for &corner_y in [offset, offset + page_height].iter() {
+                let out_x = if corner_x == offset { -1.0 } else { 1.0 };
+                let out_y = if corner_y == offset { -1.0 } else { 1.0 };
+                canvas.line(corner_x + out_x * bleed, corner_y, corner_x + out_x * offset, corner_y);
+                canvas.line(corner_x, corner_y + out_y * bleed, corner_x, corner_y + out_y * offset);
+            }
+        }
+    }
+
+    // Assemble the document
+    let mut pdf = Pdf::new();
+    let mut next = Ref::new(1);
+    let catalog_id = next.bump();
+    let pages_id = next.bump();
+    let page_id = next.bump();
+    let content_id = next.bump();
+    let info_id = next.bump();
+    let font_refs = [next.bump(), next.bump(), next.bump(), next.bump(), next.bump()];
+    let states : Vec<Ref> = OPACITIES.iter().map(|_| next.bump()).collect();
+    let logo_refs = (next.bump(), next.bump());
+
+    pdf.catalog(catalog_id).pages(pages_id);
+    pdf.pages(pages_id).kids([page_id]).count(1);
+    pdf.document_info(info_id)
+        .title(TextStr(&options.title))
+        .creator(TextStr("lod-cloud-draw"));
+
+    let mut page = pdf.page(page_id);
+    page.parent(pages_id)
+        .media_box(Rect::new(0.0, 0.0, media_width as f32, media_height as f32))
+        .bleed_box(Rect::new((offset - bleed) as f32, (offset - bleed) as f32,
+                             (offset + page_width + bleed) as f32, (offset + page_height + bleed) as f32))
+        .trim_box(Rect::new(offset as f32, offset as f32,
+                            (offset + page_width) as f32, (offset + page_height) as f32))
+        .contents(content_id);
+    let mut resources = page.resources();
+    resources.fonts().pair(Name(b"F1"), font_refs[0]);
+    resources.ext_g_states().pairs(OPACITIES.iter().zip(states.iter())
+                                   .map(|((name, _, _), id)| (Name(name), *id)));
+    if logo.is_some() {
+        resources.x_objects().pair(Name(b"Logo"), logo_refs.0);
+    }
+    resources.finish();
+    page.finish();
+
+    for ((_, fill, stroke), id) in OPACITIES.iter().zip(states.iter()) {
+        pdf.ext_graphics(*id).non_stroking_alpha(*fill).stroking_alpha(*stroke);
+    }
+    let content = compress_to_vec_zlib(&canvas.content.finish(), COMPRESSION);
+    pdf.stream(content_id, &content).filter(Filter::FlateDecode);
+    canvas.font.write(&mut pdf, font_refs);
+    if let Some(ref logo) = logo {
+        write_logo(&mut pdf, logo, logo_refs.0, logo_refs.1);
+    }
+
+    Ok(pdf.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pdf::*;
+
+    #[test]
+    fn test_page_size() {
+        assert_eq!(page_size("A4").unwrap(), (210.0, 297.0));
+        assert_eq!(page_size("500x700").unwrap(), (500.0, 700.0));
+        assert!(page_size("a9").is_err());
+        assert!(page_size("0x100").is_err());
+    }
+
+    #[test]
+    fn test_styles() {
+        let removed = link_stroke(true, Some("link-removed"));
+        assert_eq!(removed.colour, "#be1b38");
+        assert_eq!(removed.dash, Some((6.0, 4.0)));
+        assert_eq!(link_stroke(true, None).width, 2.5);
+        let changed = bubble_stroke(Some(Availability::Unavailable), Some("changed"));
+        assert_eq!(changed.colour, "#f6a000");
+        assert_eq!(changed.dash, Some((6.0, 4.0)));
+        assert_eq!(rgb("#ff0000"), (1.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,195 @@
+//! Rendering of the SVG clouds to PNG or JPEG images
+use crate::scene::{Renderer, Scene};
+use crate::svg::SvgRenderer;
+use jpeg_encoder::{ColorType, Encoder};
+use resvg::tiny_skia::{Color, Pixmap, PixmapPaint, Transform};
+use resvg::usvg::{Options, Tree};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// The resolution of SVG user units
+const SVG_DPI : f64 = 96.0;
+
+/// The quality of JPEG images
+const JPEG_QUALITY : u8 = 90;
+
+/// The fonts used for text without an installed font, in order of preference
+pub const FALLBACK_FONTS : [&str; 5] = ["Verdana", "Arial", "DejaVu Sans", "Liberation Sans", "FreeSans"];
+
+/// The options for rendering an image
+#[derive(Clone,Debug,PartialEq)]
+pub struct RasterOptions {
+    /// The resolution of the image
+    pub dpi : f64,
+    /// The width of the image in pixels, overriding `dpi`
+    pub width : Option<u32>,
+    /// The background colour, transparent if not given
+    pub background : Option<String>,
+    /// A file to write a smaller copy of the image to
+    pub thumbnail : Option<String>,
+    /// The size of the thumbnail relative to the image
+    pub thumbnail_scale : f64
+}
+
+impl Default for RasterOptions {
+    fn default() -> RasterOptions {
+        RasterOptions {
+            dpi: 300.0,
+            width: None,
+            background: None,
+            thumbnail: None,
+            thumbnail_scale: 0.4
+        }
+    }
+}
+
+/// Writes scenes as PNG or JPEG images by drawing their SVG
+pub struct RasterRenderer {
+    /// The renderer of the SVG that is drawn
+    pub svg : SvgRenderer,
+    pub options : RasterOptions,
+    /// Whether to write JPEG instead of PNG
+    pub jpeg : bool
+}
+
+impl Renderer for RasterRenderer {
+    fn render(&self, scene : &Scene, out : &mut dyn Write) -> Result<(), String> {
+        let mut svg = Vec::new();
+        self.svg.render(scene, &mut svg)?;
+        let (image, thumbnail) = draw(&svg, &self.options)?;
+        encode(&image, self.jpeg, out)?;
+        if let (Some(pixmap), Some(path)) = (thumbnail, self.options.thumbnail.as_ref()) {
+            save(&pixmap, Path::new(path))?;
+        }
+        Ok(())
+    }
+}
+
+/// Render an SVG image to a PNG (or JPEG) file, and its thumbnail if 
+/// requested. The format of each file is chosen by its extension.
+pub fn write_raster<P : AsRef<Path>>(svg : &[u8], options : &RasterOptions,
+                                     out_file : P) -> Result<(), String> {
+    let (image, thumbnail) = draw(svg, options)?;
+    save(&image, out_file.as_ref())?;
+    if let (Some(pixmap), Some(path)) = (thumbnail, options.thumbnail.as_ref()) {
+        save(&pixmap, Path::new(path))?;
+    }
+    Ok(())
+}
+
+/// Draw an SVG image, and its thumbnail if requested
+fn draw(svg : &[u8], options : &RasterOptions) -> Result<(Pixmap, Option<Pixmap>), String> {
+    let tree = Tree::from_data(svg, &font_options()).map_err(|e| format!("Could not read SVG: {}", e))?;
+
+    let scale = match options.width {
+        Some(width) => width as f64 / tree.size().width() as f64,
+        None => options.dpi / SVG_DPI
+    };
+    let background = match options.background {
+        Some(ref colour) => Some(parse_colour(colour)?),
+        None => None
+    };
+
+    let image = render(&tree, scale, background)?;
+    let thumbnail = match options.thumbnail {
+        Some(_) => Some(render(&tree, scale * options.thumbnail_scale, background)?),
+        None => None
+    };
+    Ok((image, thumbnail))
+}
+
+/// Is this the path of a JPEG file
+pub fn is_jpeg(path : &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("jpg") || e.eq_ignore_ascii_case("jpeg"))
+        .unwrap_or(false)
+}
+
+/// Save an image as PNG or JPEG, chosen by the extension of the file
+fn save(pixmap : &Pixmap, path : &Path) -> Result<(), String> {
+    let file = File::create(path)
+        .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+    let mut out = BufWriter::new(file);
+    encode(pixmap, is_jpeg(path), &mut out)?;
+    out.flush().map_err(|e| format!("Could not write {}: {}", path.display(), e))
+}
+
+/// Encode an image as PNG or JPEG, where transparent parts of a JPEG become 
+/// white
+fn encode(pixmap : &Pixmap, jpeg : bool, out : &mut dyn Write) -> Result<(), String> {
+    if jpeg {
+        let mut opaque = Pixmap::new(pixmap.width(), pixmap.height())
+            .ok_or("Cannot create image")?;
+        opaque.fill(Color::WHITE);
+        opaque.draw_pixmap(0, 0, pixmap.as_ref(), &PixmapPaint::default(), Transform::identity(), None);
+        let width = u16::try_from(opaque.width()).map_err(|_| "Image too wide for JPEG")?;
+        let height = u16::try_from(opaque.height()).map_err(|_| "Image too high for JPEG")?;
+        Encoder::new(out, JPEG_QUALITY)
+            .encode(opaque.data(), width, height, ColorType::Rgba)
+            .map_err(|e| format!("Could not write JPEG: {}", e))
+    } else {
+        let png = pixmap.encode_png().map_err(|e| format!("Could not write PNG: {}", e))?;
+        out.write_all(&png).map_err(|e| format!("Could not write PNG: {}", e))
+    }
+}
+
+/// Load the system fonts, using the first installed fallback font for any
+/// text whose font is not installed
+pub fn font_options() -> Options<'static> {
+    let mut options = Options::default();
+    let fontdb = options.fontdb_mut();
+    fontdb.load_system_fonts();
+    let fallback = FALLBACK_FONTS.iter().find(|name| {
+        fontdb.faces().any(|f| f.families.iter().any(|(family, _)| family == *name))
+    });
+    if let Some(name) = fallback {
+        fontdb.set_serif_family(*name);
+        fontdb.set_sans_serif_family(*name);
+        options.font_family = name.to_string();
+    }
+    options
+}
+
+/// Draw an SVG image at the given scale
+pub fn render(tree : &Tree, scale : f64, background : Option<Color>) -> Result<Pixmap, String> {
+    let width = (tree.size().width() as f64 * scale).ceil() as u32;
+    let height = (tree.size().height() as f64 * scale).ceil() as u32;
+    let mut pixmap = Pixmap::new(width, height)
+        .ok_or_else(|| format!("Cannot create an image of {}x{} pixels", width, height))?;
+    if let Some(colour) = background {
+        pixmap.fill(colour);
+    }
+    resvg::render(tree, Transform::from_scale(scale as f32, scale as f32), &mut pixmap.as_mut());
+    Ok(pixmap)
+}
+
+/// Parse a CSS colour, such as "white" or "#ffe0c0"
+pub fn parse_colour(colour : &str) -> Result<Color, String> {
+    let c = svgtypes::Color::from_str(colour)
+        .map_err(|_| format!("{} is not a valid colour", colour))?;
+    Ok(Color::from_rgba8(c.red, c.green, c.blue, c.alpha))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::raster::*;
+
+    #[test]
+    fn test_render() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50">
+            <circle cx="25" cy="25" r="20" fill="red"/></svg>"#;
+        let tree = Tree::from_data(svg, &Options::default()).unwrap();
+        let pixmap = render(&tree, 2.0, Some(parse_colour("white").unwrap())).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (200, 100));
+        let centre = pixmap.pixel(50, 50).unwrap();
+        assert_eq!((centre.red(), centre.green(), centre.blue()), (255, 0, 0));
+        let corner = pixmap.pixel(199, 99).unwrap();
+        assert_eq!((corner.red(), corner.green(), corner.blue()), (255, 255, 255));
+        assert!(parse_colour("not a colour").is_err());
+        assert!(is_jpeg(Path::new("cloud-sm.JPG")));
+        assert!(!is_jpeg(Path::new("cloud.png")));
+    }
+}
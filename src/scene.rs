@@ -0,0 +1,768 @@
+//! The scene of a cloud: the bubbles, links, labels, legend and footer to be
+//! drawn, computed once from the graph, the positions of its vertices, the
+//! data and the settings. Each output format is written from the scene by a
+//! `Renderer`
+use crate::bundling;
+use crate::data::{Availability, Dataset};
+use crate::graph::{self, Edge, Graph};
+use crate::label::bubble_label;
+use crate::settings::Settings;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// The width of a letter of the legend and rights text
+pub const LETTER_WIDTH : f64 = 17.0;
+/// The height of the footer below the cloud with the rights text and logo
+pub const LINE_HEIGHT : f64 = 42.0;
+/// The size of the cloud at which the legend is drawn at its full size
+const LEGEND_FULL_SIZE : f64 = 1250.0;
+/// The smallest scale the legend is drawn at
+const MIN_LEGEND_SCALE : f64 = 0.5;
+/// The smallest width of the boxes of the legend
+const MIN_LEGEND_WIDTH : f64 = 310.0;
+/// The width of the default (CC BY) logo
+pub const LOGO_WIDTH : usize = 120;
+/// The font size of the labels of the bubbles
+pub const LABEL_SIZE : f64 = 8.0;
+/// The length of an arrowhead at the end of a link
+pub const ARROW_LENGTH : f64 = 8.0;
+/// The width of the link with the fewest links when links are weighted
+const MIN_LINK_WIDTH : f64 = 0.5;
+/// The width of the link with the most links when links are weighted
+const MAX_LINK_WIDTH : f64 = 6.0;
+/// The opacity of the link with the fewest links when links are weighted
+const MIN_LINK_OPACITY : f64 = 0.15;
+/// The opacity of the link with the most links when links are weighted
+const MAX_LINK_OPACITY : f64 = 0.9;
+/// The most entries of the legend of the link weights
+const MAX_WEIGHT_ENTRIES : usize = 4;
+/// The page of a dataset that its bubble links to by default
+const DEFAULT_LINK_TEMPLATE : &str = "https://lod-cloud.net/dataset/{identifier}";
+
+/// Extra classes given to bubbles and links, e.g., to show the changes
+/// between two versions of the cloud
+#[derive(Debug,Clone,Default)]
+pub struct Highlights {
+    /// The class of the bubble of a dataset
+    pub bubbles : HashMap<String, String>,
+    /// The class of the link between two datasets
+    pub links : HashMap<(String, String), String>
+}
+
+/// A way of writing a scene
+pub trait Renderer {
+    /// Write the scene
+    fn render(&self, scene : &Scene, out : &mut dyn Write) -> Result<(), String>;
+
+    /// Write the scene to a file
+    fn write_file(&self, scene : &Scene, out_file : &Path) -> Result<(), String> {
+        let file = File::create(out_file)
+            .map_err(|e| format!("Could not write {}: {}", out_file.display(), e))?;
+        let mut out = BufWriter::new(file);
+        self.render(scene, &mut out)?;
+        out.flush().map_err(|e| format!("Could not write {}: {}", out_file.display(), e))
+    }
+}
+
+/// Everything to be drawn for a cloud, where positions are in a square of
+/// `size` with the origin at the top left
+#[derive(Debug,Clone)]
+pub struct Scene {
+    /// The width and height of the cloud
+    pub size : f64,
+    pub bubbles : Vec<Bubble>,
+    pub lines : Vec<Line>,
+    pub legend : Vec<LegendItem>,
+    pub rights_text : Option<String>,
+    pub logo : Logo,
+    /// Whether links end in arrowheads at the bubble they point to
+    pub arrows : bool,
+    /// Examples of the weights of links, shown beside the legend if the
+    /// links are weighted
+    pub weight_legend : Vec<WeightItem>
+}
+
+/// The bubble of a dataset
+#[derive(Debug,Clone)]
+pub struct Bubble {
+    /// The vertex of the dataset in the graph
+    pub vertex : usize,
+    pub identifier : String,
+    pub title : String,
+    /// The lines of text shown in the bubble, if any
+    pub label : Vec<String>,
+    /// The font size of the label
+    pub label_size : f64,
+    pub description : Option<String>,
+    pub domain : String,
+    /// The index of the legend entry of the dataset, if any
+    pub group : Option<usize>,
+    pub colour : String,
+    pub triples : i64,
+    /// The page of the dataset that the bubble links to, if any
+    pub url : Option<String>,
+    pub x : f64,
+    pub y : f64,
+    pub r : f64,
+    /// The availability of the dataset, if it is to be shown
+    pub availability : Option<Availability>,
+    /// Any extra class of the bubble
+    pub highlight : Option<String>
+}
+
+/// A link between two bubbles, which is either straight or a quadratic
+/// curve
+#[derive(Debug,Clone)]
+pub struct Line {
+    /// The index of the bubble the link is from
+    pub source : usize,
+    /// The index of the bubble the link is to
+    pub target : usize,
+    pub x1 : f64,
+    pub y1 : f64,
+    pub x2 : f64,
+    pub y2 : f64,
+    /// The control point of a curved link
+    pub control : Option<(f64, f64)>,
+    /// The number of links from the source to the target
+    pub value : i64,
+    /// The number of links back, if the link is bidirectional
+    pub reverse : Option<i64>,
+    /// Any extra class of the link
+    pub highlight : Option<String>,
+    /// The width and opacity of the link, if it is weighted
+    pub weight : Weight,
+    /// The points a bundled link passes through between its ends
+    pub bundle : Vec<(f64, f64)>
+}
+
+impl Line {
+    /// Whether this line stands for the links in both directions
+    pub fn is_bidirectional(&self) -> bool {
+        self.reverse.is_some()
+    }
+
+    /// The quadratic curves (start, control point and end) that a bundled
+    /// link is drawn as. Each curve runs between the midpoints of the lines
+    /// through the points of the link, so that the link is smooth
+    pub fn bundle_curves(&self) -> Vec<(Point, Point, Point)> {
+        let mut points = vec![(self.x1, self.y1)];
+        points.extend_from_slice(&self.bundle);
+        points.push((self.x2, self.y2));
+        let n = points.len();
+        let mid = |a : Point, b : Point| ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+        (1..n - 1).map(|i| {
+            let start = if i == 1 { points[0] } else { mid(points[i - 1], points[i]) };
+            let end = if i == n - 2 { points[n - 1] } else { mid(points[i], points[i + 1]) };
+            (start, points[i], end)
+        }).collect()
+    }
+}
+
+/// An entry of the legend
+#[derive(Debug,Clone,PartialEq)]
+pub struct LegendItem {
+    pub title : String,
+    pub colour : String,
+    /// The availability shown by the outline of this entry, if any
+    pub availability : Option<Availability>
+}
+
+/// How the number of links between two datasets is shown
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkWeight {
+    /// Links with more links are wider
+    Width,
+    /// Links with more links are more opaque
+    Opacity,
+    /// Links with more links are wider and more opaque
+    Both
+}
+
+/// The width and opacity of a link, which are those of its style if not
+/// given
+#[derive(Debug,Clone,Copy,PartialEq,Default)]
+pub struct Weight {
+    pub width : Option<f64>,
+    pub opacity : Option<f64>
+}
+
+/// An entry of the legend of the link weights
+#[derive(Debug,Clone,PartialEq)]
+pub struct WeightItem {
+    /// The number of links
+    pub value : i64,
+    pub weight : Weight
+}
+
+/// The logo shown in the bottom right corner
+#[derive(Debug,Clone,PartialEq)]
+pub enum Logo {
+    /// The Creative Commons Attribution logo
+    Default,
+    /// An image from a link
+    Image { href : String, width : usize }
+}
+
+impl Scene {
+    /// Compute the scene of a graph with a set of locations, where the cloud
+    /// fills at least the well
+    pub fn new(graph : &Graph, loc : &Vec<f64>, data : &HashMap<String, Dataset>,
+               well_size : f64, settings : &Settings, highlights : &Highlights) -> Scene {
+        let mut abs_max = list_abs_max(loc) * 1.05;
+        if abs_max < well_size {
+            abs_max = well_size;
+        }
+        eprintln!("Final well size: {:.3}", abs_max);
+
+        let metric_sizes = settings.bubble_size_by.map(|m| scale_metric(&m.compute(graph)));
+        let show_availability = settings.show_availability.unwrap_or(false);
+        let link_template = settings.link_template.as_deref().unwrap_or(DEFAULT_LINK_TEMPLATE);
+        let mut index = vec![None; graph.n];
+        let mut bubbles = Vec::new();
+        for (i, name) in graph.vertices() {
+            let dataset = match data.get(name) {
+                Some(dataset) => dataset,
+                None => {
+                    eprintln!("Dataset not in set: {} (maybe `identifier` is incorrect?)", name);
+                    continue;
+                }
+            };
+            let title = dataset.title.clone().unwrap_or_else(|| "Unnamed dataset".to_string());
+            let group = legend_entry(&dataset.domain, &dataset.keywords, settings);
+            let r = metric_sizes.as_ref().map(|s| s[i]).unwrap_or_else(|| {
+                bubble_radius(dataset, settings.bubble_size_factor.unwrap_or(10.0))
+            });
+            let label = bubble_label(&title, &dataset.identifier, r, settings);
+            index[i] = Some(bubbles.len());
+            bubbles.push(Bubble {
+                vertex: i,
+                identifier: dataset.identifier.clone(),
+                label: label.lines,
+                label_size: label.size,
+                title,
+                description: dataset.description_text().map(|d| d.to_string()),
+                domain: dataset.domain.clone(),
+                group,
+                colour: group.map(|g| settings.legend[g].colour.clone())
+                    .unwrap_or_else(|| "white".to_string()),
+                triples: dataset.triples.get(),
+                url: dataset_link(dataset, link_template),
+                x: loc[i * 2] + abs_max,
+                y: loc[i * 2 + 1] + abs_max,
+                r,
+                availability: if show_availability { Some(dataset.availability()) } else { None },
+                highlight: highlights.bubbles.get(name).cloned()
+            });
+        }
+
+        let arrows = settings.arrows.unwrap_or(false);
+        let bundle_strength = settings.edge_bundling.filter(|s| *s != 0.0);
+        // Bundled links replace curved links, and start and end towards the
+        // centres of their bubbles
+        let curvature = if bundle_strength.is_some() { 0.0 } else { settings.link_curvature.unwrap_or(0.0) };
+        let scale = settings.link_weight.map(|w| WeightScale::new(w, graph.edges().iter().filter(|edge| {
+            index[edge.src].is_some() && index[edge.trg].is_some()
+        }).map(link_count)));
+        let mut lines : Vec<Line> = graph.edges().iter().filter_map(|edge| {
+            let (source, target) = (index[edge.src]?, index[edge.trg]?);
+            let (start, end, control) = link_path(&bubbles[source], &bubbles[target], curvature, arrows);
+            Some(Line {
+                source,
+                target,
+                x1: start.0,
+                y1: start.1,
+                x2: end.0,
+                y2: end.1,
+                control,
+                value: edge.value,
+                reverse: edge.reverse,
+                highlight: link_highlight(graph, edge, highlights).cloned(),
+                weight: scale.as_ref().map(|s| s.weight(link_count(edge))).unwrap_or_default(),
+                bundle: Vec::new()
+            })
+        }).collect();
+        if let Some(strength) = bundle_strength {
+            let ends : Vec<_> = lines.iter().map(|l| ((l.x1, l.y1), (l.x2, l.y2))).collect();
+            for (line, points) in lines.iter_mut().zip(bundling::bundle(&ends, strength)) {
+                line.bundle = points;
+            }
+        }
+        let weight_legend = match scale {
+            Some(ref s) if !lines.is_empty() => s.legend(),
+            _ => Vec::new()
+        };
+
+        let mut legend : Vec<LegendItem> = settings.legend.iter().map(|e| LegendItem {
+            title: e.title.clone(),
+            colour: e.colour.clone(),
+            availability: None
+        }).collect();
+        if show_availability {
+            for (availability, title) in [(Availability::Unavailable, "No working access point"),
+                                          (Availability::Unknown, "Availability unknown")] {
+                legend.push(LegendItem {
+                    title: title.to_string(),
+                    colour: "white".to_string(),
+                    availability: Some(availability)
+                });
+            }
+        }
+
+        // The datasets without links that are left out are counted below the
+        // rights text
+        let excluded = match settings.unlinked {
+            Some(_) => 0,
+            None => graph::unlinked_selected(data, settings).len()
+        };
+        let rights_text = match (settings.rights_text.clone(), excluded) {
+            (rights_text, 0) => rights_text,
+            (None, n) => Some(excluded_note(n)),
+            (Some(rt), n) => Some(format!("{} {}", rt, excluded_note(n)))
+        };
+
+        Scene {
+            size: abs_max * 2.0,
+            bubbles,
+            lines,
+            legend,
+            rights_text,
+            logo: match settings.logo_link {
+                Some(ref l) => Logo::Image {
+                    href: l.clone(),
+                    width: settings.logo_width.unwrap_or(LOGO_WIDTH)
+                },
+                None => Logo::Default
+            },
+            arrows,
+            weight_legend
+        }
+    }
+
+    /// The scale of the legend, which grows with clouds larger than
+    /// `LEGEND_FULL_SIZE`
+    pub fn legend_scale(&self) -> f64 {
+        MIN_LEGEND_SCALE.max(self.size / 2.0 / LEGEND_FULL_SIZE)
+    }
+
+    /// The width of the boxes of the legend, to fit the longest title
+    pub fn legend_width(&self) -> f64 {
+        let longest = self.legend.iter().map(|e| e.title.chars().count()).max().unwrap_or(0);
+        MIN_LEGEND_WIDTH.max(longest as f64 * LETTER_WIDTH)
+    }
+
+    /// The scale of the rights text, which shrinks to fit below the cloud
+    pub fn rights_scale(&self) -> f64 {
+        self.rights_text.as_ref().map(|rt| {
+            (self.size * 1.5 / (rt.chars().count() + 1) as f64 / LETTER_WIDTH).min(1.0)
+        }).unwrap_or(1.0)
+    }
+
+    /// The class of the outline of a bubble showing its availability, if any
+    pub fn availability_class(availability : Option<Availability>) -> Option<&'static str> {
+        match availability {
+            Some(Availability::Unavailable) => Some("unavailable"),
+            Some(Availability::Unknown) => Some("availability-unknown"),
+            _ => None
+        }
+    }
+}
+
+/// The number of links in both directions of an edge
+fn link_count(edge : &Edge) -> i64 {
+    edge.value + edge.reverse.unwrap_or(0)
+}
+
+/// A logarithmic scale from numbers of links to the widths and opacities of
+/// links
+struct WeightScale {
+    link_weight : LinkWeight,
+    min : i64,
+    max : i64
+}
+
+impl WeightScale {
+    fn new(link_weight : LinkWeight, values : impl Iterator<Item=i64>) -> WeightScale {
+        let (min, max) = values.fold((i64::MAX, 1), |(min, max), v| (min.min(v.max(1)), max.max(v)));
+        WeightScale { link_weight, min: min.min(max), max }
+    }
+
+    fn weight(&self, value : i64) -> Weight {
+        let t = if self.max > self.min {
+            ((value.clamp(self.min, self.max) as f64).ln() - (self.min as f64).ln())
+                / ((self.max as f64).ln() - (self.min as f64).ln())
+        } else {
+            1.0
+        };
+        let width = MIN_LINK_WIDTH + t * (MAX_LINK_WIDTH - MIN_LINK_WIDTH);
+        let opacity = MIN_LINK_OPACITY + t * (MAX_LINK_OPACITY - MIN_LINK_OPACITY);
+        match self.link_weight {
+            LinkWeight::Width => Weight { width: Some(width), opacity: None },
+            LinkWeight::Opacity => Weight { width: None, opacity: Some(opacity) },
+            LinkWeight::Both => Weight { width: Some(width), opacity: Some(opacity) }
+        }
+    }
+
+    /// The powers of ten between the fewest and most links, or the fewest
+    /// and most links themselves if there are not two such powers
+    fn legend(&self) -> Vec<WeightItem> {
+        let mut values : Vec<i64> = (0..19).map(|e| 10i64.pow(e))
+            .filter(|v| *v >= self.min && *v <= self.max).collect();
+        if values.len() < 2 {
+            values = vec![self.min, self.max];
+            values.dedup();
+        }
+        let step = values.len().div_ceil(MAX_WEIGHT_ENTRIES);
+        values.into_iter().step_by(step).map(|value| WeightItem {
+            value,
+            weight: self.weight(value)
+        }).collect()
+    }
+}
+
+/// A position in the scene
+pub type Point = (f64, f64);
+
+/// The start, end and control point (if curved) of a link. Links bend to
+/// their right by `curvature` times their length, so that the links in both 
+/// directions between two bubbles are separate arcs. Links with arrowheads
+/// start and end at the outlines of the bubbles, unless they overlap
+fn link_path(source : &Bubble, target : &Bubble, curvature : f64, arrows : bool)
+        -> (Point, Point, Option<Point>) {
+    let (dx, dy) = (target.x - source.x, target.y - source.y);
+    let control = if curvature != 0.0 {
+        Some(((source.x + target.x) / 2.0 - dy * curvature,
+              (source.y + target.y) / 2.0 + dx * curvature))
+    } else {
+        None
+    };
+    let distance = (dx * dx + dy * dy).sqrt();
+    if !arrows || distance <= source.r + target.r {
+        return ((source.x, source.y), (target.x, target.y), control);
+    }
+    // The ends are moved along the tangents of the curve, which point to the
+    // control point
+    let towards = |from : &Bubble, (x, y) : Point| {
+        let length = ((x - from.x).powi(2) + (y - from.y).powi(2)).sqrt();
+        (from.x + (x - from.x) * from.r / length, from.y + (y - from.y) * from.r / length)
+    };
+    (towards(source, control.unwrap_or((target.x, target.y))),
+     towards(target, control.unwrap_or((source.x, source.y))),
+     control)
+}
+
+/// The extra class of a link, if any. A bidirectional link also takes the
+/// class of the link in the reverse direction
+fn link_highlight<'a>(graph : &Graph, edge : &Edge, highlights : &'a Highlights) -> Option<&'a String> {
+    if highlights.links.is_empty() {
+        return None;
+    }
+    graph.vertex_name(edge.src).zip(graph.vertex_name(edge.trg))
+        .and_then(|(s, t)| {
+            highlights.links.get(&(s.clone(), t.clone())).or_else(|| {
+                if edge.is_bidirectional() {
+                    highlights.links.get(&(t, s))
+                } else {
+                    None
+                }
+            })
+        })
+}
+
+/// The largest absolute value of the finite coordinates
+fn list_abs_max(xs : &Vec<f64>) -> f64 {
+    let mut max = 0.0;
+    for x in xs {
+        if x.is_finite() && x.abs() > max {
+            max = x.abs()
+        }
+    }
+    max
+}
+
+/// The index of the first legend entry matching a dataset, by its domain or
+/// keywords
+fn legend_entry(domain : &str, keywords : &[String], settings : &Settings) -> Option<usize> {
+    for (i, e) in settings.legend.iter().enumerate() {
+        if let Some(ref d) = e.domain {
+            if domain == d {
+                return Some(i)
+            }
+        }
+        if let Some(ref tags) = e.keywords {
+            for t in tags.iter() {
+                if keywords.contains(t) {
+                    return Some(i)
+                }
+            }
+            if tags.is_empty() {
+                return Some(i)
+            }
+        }
+    }
+    None
+}
+
+/// The note of the number of datasets without links that are not shown
+fn excluded_note(n : usize) -> String {
+    if n == 1 {
+        "1 dataset without links is not shown.".to_string()
+    } else {
+        format!("{} datasets without links are not shown.", n)
+    }
+}
+
+/// The placeholders that can be used in a link template
+const LINK_PLACEHOLDERS : [&str; 5] = ["identifier", "domain", "title", "website", "doi"];
+
+/// Check that a link template only uses known placeholders
+pub fn check_link_template(template : &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some((_, placeholder, after)) = split_placeholder(rest) {
+        if !LINK_PLACEHOLDERS.contains(&placeholder) {
+            return Err(format!("{{{}}} is not a placeholder of the link template (use {})",
+                               placeholder, LINK_PLACEHOLDERS.map(|p| format!("{{{}}}", p)).join(", ")));
+        }
+        rest = after;
+    }
+    Ok(())
+}
+
+/// Split a template at its first placeholder into the text before it, the
+/// name of the placeholder and the text after it. A `{` that is not closed
+/// is not a placeholder
+fn split_placeholder(template : &str) -> Option<(&str, &str, &str)> {
+    let start = template.find('{')?;
+    let end = start + template[start..].find('}')?;
+    Some((&template[..start], &template[start + 1..end], &template[end + 1..]))
+}
+
+/// The page of a dataset that its bubble links to, by filling in the
+/// placeholders of the template. There is no link if the template is empty or
+/// uses a value the dataset does not have
+fn dataset_link(dataset : &Dataset, template : &str) -> Option<String> {
+    if template.is_empty() {
+        return None;
+    }
+    let mut link = String::new();
+    let mut rest = template;
+    while let Some((before, placeholder, after)) = split_placeholder(rest) {
+        link.push_str(before);
+        let value = match placeholder {
+            "identifier" => Some(encode_component(&dataset.identifier)),
+            "domain" => Some(encode_component(&dataset.domain)),
+            "title" => dataset.title.as_deref().map(encode_component),
+            "website" => dataset.website.clone(),
+            "doi" => dataset.doi.clone(),
+            _ => None
+        };
+        link.push_str(value.as_deref().map(str::trim).filter(|v| !v.is_empty())?);
+        rest = after;
+    }
+    link.push_str(rest);
+    Some(link)
+}
+
+/// Percent-encode a value to be used as part of a URL
+fn encode_component(value : &str) -> String {
+    let mut encoded = String::new();
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
+
+/// The radius of a bubble by the number of triples of the dataset
+fn bubble_radius(dataset : &Dataset, factor : f64) -> f64 {
+    let size = (dataset.triples.get() as f64) + 1.0;
+    15.0 + size.log(factor)
+}
+
+/// Scale the values of a metric to bubble sizes, where the radius grows with
+/// the square root of the value, from 15 to 35 for the largest
+fn scale_metric(values : &[f64]) -> Vec<f64> {
+    let max = values.iter().cloned().fold(0.0, f64::max);
+    values.iter().map(|v| {
+        if max > 0.0 {
+            15.0 + 20.0 * (v / max).sqrt()
+        } else {
+            15.0
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scene::*;
+
+    #[test]
+    fn test_scene() {
+        let mut data = HashMap::new();
+        for (id, domain) in [("a", "media"), ("b", "geography")] {
+            data.insert(id.to_string(), Dataset {
+                identifier: id.to_string(),
+                domain: domain.to_string(),
+                ..Default::default()
+            });
+        }
+        let mut graph = Graph::new();
+        graph.add_vertex("a");
+        graph.add_vertex("b");
+        graph.add_vertex("missing");
+        graph.add_edge(Edge::new(0, 1));
+        graph.add_edge(Edge::new(2, 1));
+        let settings : Settings = serde_json::from_str(r##"{
+            "legend": [{"title": "Geography", "domain": "geography", "colour": "#29c9cc"}],
+            "fixed_points": {},
+            "show_availability": true
+        }"##).unwrap();
+        let mut highlights = Highlights::default();
+        highlights.links.insert(("a".to_string(), "b".to_string()), "link-added".to_string());
+        let scene = Scene::new(&graph, &vec![-10.0, 0.0, 10.0, 5.0, 0.0, 0.0], &data, 5.0,
+                               &settings, &highlights);
+        assert_eq!(scene.bubbles.len(), 2);
+        assert_eq!(scene.lines.len(), 1);
+        assert_eq!(scene.lines[0].highlight, Some("link-added".to_string()));
+        assert_eq!(scene.bubbles[0].group, None);
+        assert_eq!(scene.bubbles[0].colour, "white");
+        assert_eq!(scene.bubbles[1].group, Some(0));
+        assert_eq!(scene.bubbles[1].colour, "#29c9cc");
+        assert_eq!(scene.bubbles[1].availability, Some(Availability::Unknown));
+        assert_eq!(scene.size, 21.0);
+        assert_eq!(scene.bubbles[0].x, 0.5);
+        assert_eq!(scene.legend.len(), 3);
+        assert_eq!(scene.logo, Logo::Default);
+        assert_eq!(scene.legend_scale(), 0.5);
+        assert_eq!(scene.legend_width(), 391.0);
+        assert_eq!(scene.bubbles[0].url, Some("https://lod-cloud.net/dataset/a".to_string()));
+        // Neither dataset has links in the data, so both are counted as left out
+        assert_eq!(scene.rights_text, Some("2 datasets without links are not shown.".to_string()));
+
+        let settings : Settings = serde_json::from_str(
+            r#"{"legend": [], "fixed_points": {}, "rights_text": "CC BY", "unlinked": "grid"}"#).unwrap();
+        let scene = Scene::new(&graph, &vec![-10.0, 0.0, 10.0, 5.0, 0.0, 0.0], &data, 5.0,
+                               &settings, &highlights);
+        assert_eq!(scene.rights_text, Some("CC BY".to_string()));
+        assert!(serde_json::from_str::<Settings>(
+            r#"{"legend": [], "fixed_points": {}, "unlinked": "spiral"}"#).is_err());
+    }
+
+    #[test]
+    fn test_dataset_link() {
+        let dataset = Dataset {
+            identifier: "dbpedia".to_string(),
+            title: Some("DBpedia (English)".to_string()),
+            domain: "cross_domain".to_string(),
+            website: Some("http://dbpedia.org/".to_string()),
+            doi: Some("".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(dataset_link(&dataset, "https://example.org/{domain}/{title}"),
+                   Some("https://example.org/cross_domain/DBpedia%20%28English%29".to_string()));
+        assert_eq!(dataset_link(&dataset, "{website}"), Some("http://dbpedia.org/".to_string()));
+        assert_eq!(dataset_link(&dataset, "https://doi.org/{doi}"), None);
+        assert_eq!(dataset_link(&dataset, ""), None);
+        assert_eq!(dataset_link(&dataset, "http://x/{abc"), Some("http://x/{abc".to_string()));
+        assert_eq!(dataset_link(&dataset, "http://x/{identifier}/{abc"),
+                   Some("http://x/dbpedia/{abc".to_string()));
+    }
+
+    #[test]
+    fn test_check_link_template() {
+        assert_eq!(check_link_template("https://example.org/{identifier}?t={title}"), Ok(()));
+        assert_eq!(check_link_template("http://x/{abc"), Ok(()));
+        assert!(check_link_template("https://example.org/{identifer}").is_err());
+        let settings = serde_json::from_str::<Settings>(
+            "{\"legend\": [], \"fixed_points\": {}, \"link_template\": \"{name}\"}");
+        assert!(settings.unwrap_err().to_string().contains("{name} is not a placeholder"));
+    }
+
+    #[test]
+    fn test_weight_scale() {
+        let scale = WeightScale::new(LinkWeight::Both, [10, 1, 100000, 0].into_iter());
+        assert_eq!(scale.weight(1), Weight { width: Some(MIN_LINK_WIDTH), opacity: Some(MIN_LINK_OPACITY) });
+        assert_eq!(scale.weight(100000), Weight { width: Some(MAX_LINK_WIDTH), opacity: Some(MAX_LINK_OPACITY) });
+        let middle = scale.weight(316).width.unwrap();
+        assert!((middle - (MIN_LINK_WIDTH + MAX_LINK_WIDTH) / 2.0).abs() < 0.01);
+        assert_eq!(scale.legend().iter().map(|e| e.value).collect::<Vec<_>>(), vec![1, 100, 10000]);
+        let scale = WeightScale::new(LinkWeight::Opacity, [30, 60].into_iter());
+        assert_eq!(scale.weight(30).width, None);
+        assert_eq!(scale.legend().iter().map(|e| e.value).collect::<Vec<_>>(), vec![30, 60]);
+        let scale = WeightScale::new(LinkWeight::Width, [5, 5].into_iter());
+        assert_eq!(scale.weight(5).width, Some(MAX_LINK_WIDTH));
+        assert_eq!(scale.legend().len(), 1);
+    }
+
+    #[test]
+    fn test_bundled_arrows() {
+        let mut data = HashMap::new();
+        for id in ["a", "b"] {
+            data.insert(id.to_string(), Dataset { identifier: id.to_string(), ..Default::default() });
+        }
+        let mut graph = Graph::new();
+        graph.add_vertex("a");
+        graph.add_vertex("b");
+        graph.add_edge(Edge::new(0, 1));
+        let settings : Settings = serde_json::from_str(r##"{
+            "legend": [],
+            "fixed_points": {},
+            "arrows": true,
+            "link_curvature": 0.3,
+            "edge_bundling": 1.0
+        }"##).unwrap();
+        let scene = Scene::new(&graph, &vec![-100.0, 0.0, 100.0, 0.0], &data, 5.0,
+                               &settings, &Highlights::default());
+        // The ends of a bundled link are not moved towards a control point
+        let (line, a, b) = (&scene.lines[0], &scene.bubbles[0], &scene.bubbles[1]);
+        assert_eq!(line.control, None);
+        assert_eq!((line.x1, line.y1), (a.x + a.r, a.y));
+        assert_eq!((line.x2, line.y2), (b.x - b.r, b.y));
+    }
+
+    #[test]
+    fn test_bundle_curves() {
+        let mut line = Line {
+            source: 0, target: 1, x1: 0.0, y1: 0.0, x2: 40.0, y2: 0.0, control: None,
+            value: 1, reverse: None, highlight: None, weight: Weight::default(), bundle: Vec::new()
+        };
+        assert!(line.bundle_curves().is_empty());
+        line.bundle = vec![(10.0, 10.0), (20.0, 10.0), (30.0, 10.0)];
+        assert_eq!(line.bundle_curves(), vec![
+            ((0.0, 0.0), (10.0, 10.0), (15.0, 10.0)),
+            ((15.0, 10.0), (20.0, 10.0), (25.0, 10.0)),
+            ((25.0, 10.0), (30.0, 10.0), (40.0, 0.0))
+        ]);
+    }
+
+    #[test]
+    fn test_link_path() {
+        let bubble = |x : f64, y : f64, r : f64| Bubble {
+            vertex: 0, identifier: String::new(), title: String::new(), label: Vec::new(),
+            label_size: LABEL_SIZE, description: None, domain: String::new(), group: None,
+            colour: "white".to_string(), triples: 0, url: None, x, y, r,
+            availability: None, highlight: None
+        };
+        let (a, b) = (bubble(0.0, 0.0, 2.0), bubble(10.0, 0.0, 3.0));
+        assert_eq!(link_path(&a, &b, 0.0, false), ((0.0, 0.0), (10.0, 0.0), None));
+        assert_eq!(link_path(&a, &b, 0.0, true), ((2.0, 0.0), (7.0, 0.0), None));
+        // The links in both directions bend to opposite sides
+        let (_, _, forward) = link_path(&a, &b, 0.2, false);
+        let (_, _, backward) = link_path(&b, &a, 0.2, false);
+        assert_eq!(forward, Some((5.0, 2.0)));
+        assert_eq!(backward, Some((5.0, -2.0)));
+        // Trimmed ends stay on the outlines of the bubbles
+        let (start, end, _) = link_path(&a, &b, 0.2, true);
+        assert!((start.0.hypot(start.1) - 2.0).abs() < 1e-9);
+        assert!(((end.0 - 10.0).hypot(end.1) - 3.0).abs() < 1e-9);
+        // Overlapping bubbles are joined centre to centre
+        let c = bubble(4.0, 0.0, 3.0);
+        assert_eq!(link_path(&a, &c, 0.0, true), ((0.0, 0.0), (4.0, 0.0), None));
+    }
+}
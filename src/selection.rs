@@ -0,0 +1,216 @@
+//! Selection of the datasets to be included in a cloud
+//!
+//! The `selection` in the settings is either one of the modes "all",
+//! "dataset" or "domain" (using `selected`, `hops`, `direction` and
+//! `datasets`), or an
+//! expression such as
+//!
+//! json```
+//! {
+//!   "and": [
+//!     { "or": [
+//!       { "domain": ["linguistics", "publications"] },
+//!       { "keyword": ["wordnet"] }
+//!     ] },
+//!     { "min_triples": 100000 },
+//!     { "min_degree": 2 },
+//!     { "min_core": 2 },
+//!     { "exclude": ["dbpedia"] },
+//!     { "not": { "identifier": "^universal-dependencies-" } }
+//!   ]
+//! }```
+use crate::centrality::Metric;
+use crate::data::Dataset;
+use crate::graph;
+use regex::Regex;
+use serde::de::{Deserialize, Deserializer, Error};
+use std::collections::{HashMap, HashSet};
+
+/// The value of the `selection` setting
+#[derive(Clone,Debug,PartialEq,Deserialize)]
+#[serde(untagged)]
+pub enum Selection {
+    /// One of the predefined modes
+    Mode(SelectionMode),
+    /// A selection expression
+    Expr(Expr)
+}
+
+/// The predefined selection modes
+#[derive(Clone,Copy,Debug,PartialEq,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectionMode {
+    /// All datasets are selected
+    All,
+    /// The datasets within `hops` of the `selected` dataset(s)
+    Dataset,
+    /// The datasets in the `selected` domain(s), plus any in `datasets`
+    Domain
+}
+
+/// An expression that selects datasets
+#[derive(Clone,Debug,PartialEq,Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Expr {
+    /// All of the expressions match (`{"and":[]}` selects everything)
+    And(Vec<Expr>),
+    /// Any of the expressions match
+    Or(Vec<Expr>),
+    /// The expression does not match
+    Not(Box<Expr>),
+    /// The dataset is in one of these domains
+    Domain(Vec<String>),
+    /// The dataset has one of these keywords (ignoring case)
+    Keyword(Vec<String>),
+    /// The dataset has at least this many triples
+    MinTriples(i64),
+    /// The dataset links to or is linked from at least this many datasets
+    MinDegree(usize),
+    /// The dataset is in the k-core for at least this k
+    MinCore(usize),
+    /// The dataset has at least this betweenness centrality (from 0 to 1)
+    MinBetweenness(f64),
+    /// The dataset has at least this PageRank (the ranks of all datasets sum
+    /// to 1)
+    MinPagerank(f64),
+    /// The dataset is one of these datasets
+    Datasets(Vec<String>),
+    /// The dataset is not one of these datasets
+    Exclude(Vec<String>),
+    /// The identifier of the dataset matches this regular expression
+    Identifier(Pattern)
+}
+
+/// A regular expression in a selection
+#[derive(Clone,Debug)]
+pub struct Pattern(Regex);
+
+impl PartialEq for Pattern {
+    fn eq(&self, other : &Pattern) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D>(deserializer: D) -> Result<Pattern, D::Error>
+        where D: Deserializer<'de>
+    {
+        let s = String::deserialize(deserializer)?;
+        Regex::new(&s).map(Pattern).map_err(D::Error::custom)
+    }
+}
+
+impl Expr {
+    /// An expression matching every dataset
+    pub fn everything() -> Expr {
+        Expr::And(Vec::new())
+    }
+
+    /// Check if the dataset with `key` matches this expression, where
+    /// `metrics` gives the value of each metric used by the expression for
+    /// each dataset with links
+    pub fn matches(&self, key : &str, dataset : &Dataset,
+                   metrics : &HashMap<Metric, HashMap<String, f64>>) -> bool {
+        let metric = |m : Metric| {
+            metrics.get(&m).and_then(|v| v.get(key)).cloned().unwrap_or(0.0)
+        };
+        match self {
+            Expr::And(es) => es.iter().all(|e| e.matches(key, dataset, metrics)),
+            Expr::Or(es) => es.iter().any(|e| e.matches(key, dataset, metrics)),
+            Expr::Not(e) => !e.matches(key, dataset, metrics),
+            Expr::Domain(ds) => ds.contains(&dataset.domain),
+            Expr::Keyword(ks) => dataset.keywords.iter().any(|k| {
+                ks.iter().any(|k2| k.eq_ignore_ascii_case(k2))
+            }),
+            Expr::MinTriples(t) => dataset.triples.get() >= *t,
+            Expr::MinDegree(d) => metric(Metric::Degree) >= *d as f64,
+            Expr::MinCore(k) => metric(Metric::Core) >= *k as f64,
+            Expr::MinBetweenness(b) => metric(Metric::Betweenness) >= *b,
+            Expr::MinPagerank(p) => metric(Metric::PageRank) >= *p,
+            Expr::Datasets(ds) => ds.iter().any(|d| d == key),
+            Expr::Exclude(ds) => !ds.iter().any(|d| d == key),
+            Expr::Identifier(p) => p.0.is_match(&dataset.identifier)
+        }
+    }
+
+    /// Select the datasets in `data` that match this expression
+    pub fn select(&self, data : &HashMap<String, Dataset>) -> HashMap<String, Dataset> {
+        let mut used = HashSet::new();
+        self.metrics(&mut used);
+        let metrics = if used.is_empty() {
+            HashMap::new()
+        } else {
+            let graph = graph::build_graph2(data, &Default::default());
+            used.into_iter().map(|m| (m, m.by_name(&graph))).collect()
+        };
+        data.iter()
+            .filter(|(k, d)| self.matches(k, d, &metrics))
+            .map(|(k, d)| (k.clone(), d.clone()))
+            .collect()
+    }
+
+    /// Find the metrics used by this expression
+    fn metrics(&self, used : &mut HashSet<Metric>) {
+        match self {
+            Expr::And(es) | Expr::Or(es) => es.iter().for_each(|e| e.metrics(used)),
+            Expr::Not(e) => e.metrics(used),
+            Expr::MinDegree(_) => { used.insert(Metric::Degree); },
+            Expr::MinCore(_) => { used.insert(Metric::Core); },
+            Expr::MinBetweenness(_) => { used.insert(Metric::Betweenness); },
+            Expr::MinPagerank(_) => { used.insert(Metric::PageRank); },
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{self, Dataset};
+    use crate::selection::*;
+
+    fn make_dataset(s : &str, d : &str, triples : i64, links : &[&str]) -> Dataset {
+        Dataset {
+            keywords: vec![format!("Tag-{}", d)],
+            ..data::make_dataset(s, d, triples, links)
+        }
+    }
+
+    fn select(expr : &str, data : &HashMap<String, Dataset>) -> Vec<String> {
+        let e : Expr = serde_json::from_str(expr).unwrap();
+        let mut keys : Vec<String> = e.select(data).into_keys().collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn test_select() {
+        let mut data = HashMap::new();
+        data.insert("a".to_string(), make_dataset("a", "media", 10, &["b", "c"]));
+        data.insert("b".to_string(), make_dataset("b", "media", 1000, &["a"]));
+        data.insert("c".to_string(), make_dataset("c", "geography", 100, &[]));
+        data.insert("d2".to_string(), make_dataset("d2", "government", 100, &["a"]));
+
+        assert_eq!(select(r#"{"and":[]}"#, &data), vec!["a", "b", "c", "d2"]);
+        assert_eq!(select(r#"{"domain":["media","geography"]}"#, &data), vec!["a", "b", "c"]);
+        assert_eq!(select(r#"{"keyword":["tag-government"]}"#, &data), vec!["d2"]);
+        assert_eq!(select(r#"{"min_triples":100}"#, &data), vec!["b", "c", "d2"]);
+        assert_eq!(select(r#"{"min_degree":2}"#, &data), vec!["a"]);
+        assert_eq!(select(r#"{"min_core":1}"#, &data), vec!["a", "b", "c", "d2"]);
+        assert_eq!(select(r#"{"min_core":2}"#, &data), Vec::<String>::new());
+        assert_eq!(select(r#"{"min_betweenness":0.5}"#, &data), vec!["a"]);
+        assert_eq!(select(r#"{"min_pagerank":0.3}"#, &data), vec!["a"]);
+        assert_eq!(select(r#"{"identifier":"[0-9]$"}"#, &data), vec!["d2"]);
+        assert_eq!(select(r#"{"and":[{"or":[{"domain":["media"]},{"datasets":["c"]}]},
+                                     {"exclude":["b"]}]}"#, &data), vec!["a", "c"]);
+        assert_eq!(select(r#"{"not":{"domain":["media"]}}"#, &data), vec!["c", "d2"]);
+        assert!(serde_json::from_str::<Expr>(r#"{"identifier":"("}"#).is_err());
+    }
+
+    #[test]
+    fn test_selection_mode() {
+        let s : Selection = serde_json::from_str("\"domain\"").unwrap();
+        assert_eq!(s, Selection::Mode(SelectionMode::Domain));
+        let s : Selection = serde_json::from_str(r#"{"min_degree":3}"#).unwrap();
+        assert_eq!(s, Selection::Expr(Expr::MinDegree(3)));
+    }
+}
@@ -54,6 +54,14 @@
 //!    "fixed_points": {},
 //!    "selection": "all"
 //! }```
+use crate::centrality::Metric;
+use crate::graph::Direction;
+use crate::label::LabelStrategy;
+use crate::scene::{self, LinkWeight};
+use crate::selection::{Expr, Selection, SelectionMode};
+use crate::svg::Theme;
+use crate::tree::UnlinkedLayout;
+use serde::de::{Deserialize, Deserializer, Error};
 use std::collections::{HashMap,HashSet};
 
 
@@ -64,12 +72,17 @@ pub struct Settings {
     pub legend : Vec<LegendEntry>,
     /// If any datasets should occur at a fixed position
     pub fixed_points : HashMap<String, (f64, f64)>,
-    /// Selection method: Either "all", "dataset" or "domain"
-    pub selection : Option<String>,
-    /// The value of the dataset/domain to be selected
-    pub selected : Option<String>,
+    /// Selection method: Either "all", "dataset", "domain" or a selection
+    /// expression (see `selection`)
+    pub selection : Option<Selection>,
+    /// The dataset(s)/domain(s) to be selected, either a single value or a list
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub selected : Option<Vec<String>>,
     /// The number of hops from a dataset in dataset mode
     pub hops : Option<u32>,
+    /// The direction of links followed in dataset mode: "both", "incoming" or
+    /// "outgoing"
+    pub direction : Option<Direction>,
     /// Any datasets that must be included in the data
     pub datasets : Option<HashSet<String>>,
     /// The description and copy right text
@@ -79,7 +92,117 @@ pub struct Settings {
     /// The logo width
     pub logo_width : Option<usize>,
     /// The factor of the bubble size
-    pub bubble_size_factor : Option<f64>
+    pub bubble_size_factor : Option<f64>,
+    /// Outline the datasets that have no working access point
+    pub show_availability : Option<bool>,
+    /// How to include the datasets that have no links: either "ring" (around
+    /// the cloud) or "grid" (below the cloud). If not given, they are left out.
+    pub unlinked : Option<UnlinkedLayout>,
+    /// If true, links in both directions between two datasets are drawn as a
+    /// single bidirectional link
+    pub merge_reciprocal : Option<bool>,
+    /// If true, communities of densely linked datasets are detected and used
+    /// as the domains of the datasets (replacing the legend) and to group
+    /// the datasets in the layout
+    pub communities : Option<bool>,
+    /// Only links with at least this value (number of links) are drawn
+    pub min_link_value : Option<i64>,
+    /// Only this many of the strongest links from each dataset are drawn
+    pub max_links_per_dataset : Option<usize>,
+    /// The metric used to size the bubbles instead of the number of triples:
+    /// "degree", "betweenness", "pagerank" or "core"
+    pub bubble_size_by : Option<Metric>,
+    /// The link of each bubble, where `{identifier}`, `{domain}`, `{title}`,
+    /// `{website}` and `{doi}` are replaced by the values of the dataset. An
+    /// empty template leaves out the links
+    #[serde(default, deserialize_with = "link_template")]
+    pub link_template : Option<String>,
+    /// How the titles of the datasets are fitted to their bubbles:
+    /// "truncate", "wrap", "abbreviate", "identifier" or "fit"
+    pub label : Option<LabelStrategy>,
+    /// The largest number of lines of a wrapped label
+    pub label_lines : Option<usize>,
+    /// Bubbles with a smaller radius have no label
+    pub min_label_radius : Option<f64>,
+    /// The style of the SVG output: "light", "dark", "high-contrast" or
+    /// "print"
+    pub theme : Option<Theme>,
+    /// A CSS file applied to the SVG output after the theme
+    pub stylesheet : Option<String>,
+    /// If true, the stylesheet is linked instead of included in the output
+    pub link_stylesheet : Option<bool>,
+    /// If true, links end in arrowheads at the bubble they point to (and
+    /// bidirectional links at both ends)
+    pub arrows : Option<bool>,
+    /// How far links bend to their right, relative to their length, so that
+    /// links in both directions are separate arcs (0 for straight links)
+    pub link_curvature : Option<f64>,
+    /// Whether the number of links between two datasets is shown by the
+    /// width or opacity of their link, or both
+    pub link_weight : Option<LinkWeight>,
+    /// How strongly links are bundled together, from 0 (straight links) to 1
+    #[serde(default, deserialize_with = "bundling_strength")]
+    pub edge_bundling : Option<f64>
+}
+
+impl Settings {
+    /// The expression selecting the datasets of the cloud, or `None` if the 
+    /// cloud is the neighbourhood of the `selected` dataset(s)
+    pub fn selection_expr(&self) -> Option<Expr> {
+        match (&self.selection, &self.selected) {
+            (Some(Selection::Mode(SelectionMode::Dataset)), Some(_)) => None,
+            (Some(Selection::Mode(SelectionMode::Domain)), Some(d)) => {
+                let mut es = vec![Expr::Domain(d.clone())];
+                if let Some(ref ds) = self.datasets {
+                    es.push(Expr::Datasets(ds.iter().cloned().collect()));
+                }
+                Some(Expr::Or(es))
+            },
+            (Some(Selection::Expr(e)), _) => Some(e.clone()),
+            _ => Some(Expr::everything())
+        }
+    }
+}
+
+/// Read either a single string or a list of strings
+fn one_or_many<'de, D>(deserializer : D) -> Result<Option<Vec<String>>, D::Error>
+    where D: Deserializer<'de>
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>)
+    }
+
+    Ok(match Option::<OneOrMany>::deserialize(deserializer)? {
+        Some(OneOrMany::One(s)) => Some(vec![s]),
+        Some(OneOrMany::Many(v)) => Some(v),
+        None => None
+    })
+}
+
+/// Read a link template, checking that it only uses known placeholders
+fn link_template<'de, D>(deserializer : D) -> Result<Option<String>, D::Error>
+    where D: Deserializer<'de>
+{
+    let template = Option::<String>::deserialize(deserializer)?;
+    if let Some(ref t) = template {
+        scene::check_link_template(t).map_err(D::Error::custom)?;
+    }
+    Ok(template)
+}
+
+/// Read the strength of the edge bundling, checking that it is between 0 and 1
+fn bundling_strength<'de, D>(deserializer : D) -> Result<Option<f64>, D::Error>
+    where D: Deserializer<'de>
+{
+    let strength = Option::<f64>::deserialize(deserializer)?;
+    match strength {
+        Some(s) if !(0.0..=1.0).contains(&s) =>
+            Err(D::Error::custom(format!("{} is not a bundling strength (between 0 and 1)", s))),
+        _ => Ok(strength)
+    }
 }
 
 /// A single type of data to be included in the Legend
@@ -0,0 +1,281 @@
+//! Statistics about the datasets and the graph of the cloud
+use crate::centrality::{self, Metric};
+use crate::community;
+use crate::data::Dataset;
+use crate::graph::{self, Graph};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// The statistics of a cloud
+#[derive(Debug,Clone,PartialEq,Serialize)]
+pub struct Statistics {
+    /// The number of datasets in the data
+    pub datasets : usize,
+    /// The total number of triples in all datasets
+    pub triples : i64,
+    /// The total of the link counts of all links
+    pub links : i64,
+    /// The number of links between datasets
+    pub link_count : usize,
+    /// The number of datasets in each domain ("" for unidentified datasets)
+    pub domains : BTreeMap<String, usize>,
+    /// The number of datasets whose domain was assigned by `--ident`
+    pub identified : usize,
+    /// The number of vertices in the graph
+    pub vertices : usize,
+    /// The number of edges in the graph
+    pub edges : usize,
+    /// The number of vertices having each degree
+    pub degree_distribution : BTreeMap<usize, usize>,
+    /// The vertices with the highest degree
+    pub most_linked : Vec<DegreeEntry>,
+    /// The largest k for which the k-core (where every dataset is linked to
+    /// or from at least k others) is not empty
+    pub max_core : usize,
+    /// The datasets with the highest PageRank
+    pub most_central : Vec<DegreeEntry>,
+    /// The size of each connected component, largest first
+    pub components : Vec<usize>,
+    /// The size of each community found by the Louvain method, largest first
+    pub communities : Vec<usize>,
+    /// The modularity of the communities
+    pub modularity : f64,
+    /// The datasets that are not in the graph as they have no links
+    pub unlinked : Vec<String>
+}
+
+/// The degree of a single dataset
+#[derive(Debug,Clone,PartialEq,Serialize)]
+pub struct DegreeEntry {
+    pub dataset : String,
+    pub title : Option<String>,
+    pub in_degree : usize,
+    pub out_degree : usize,
+    pub degree : usize,
+    pub betweenness : f64,
+    pub pagerank : f64,
+    pub core : usize
+}
+
+/// Calculate the statistics of the data and the graph built from it, where
+/// `identified` is the number of datasets whose domain was identified and
+/// `top` is the number of most-linked datasets to report
+pub fn statistics(data : &HashMap<String, Dataset>, graph : &Graph,
+                  identified : usize, top : usize) -> Statistics {
+    let mut domains = BTreeMap::new();
+    for dataset in data.values() {
+        *domains.entry(dataset.domain.clone()).or_insert(0) += 1;
+    }
+
+    let betweenness = Metric::Betweenness.compute(graph);
+    let pagerank = Metric::PageRank.compute(graph);
+    let core = centrality::core_numbers(graph);
+
+    let mut degree_distribution = BTreeMap::new();
+    let mut degrees = Vec::new();
+    for (i, name) in graph.vertices() {
+        let degree = graph.degree(i);
+        *degree_distribution.entry(degree).or_insert(0) += 1;
+        degrees.push(DegreeEntry {
+            title: data.get(name).and_then(|d| d.title.clone()),
+            dataset: name.to_string(),
+            in_degree: graph.in_edges(i).count(),
+            out_degree: graph.out_edges(i).count(),
+            degree,
+            betweenness: betweenness[i],
+            pagerank: pagerank[i],
+            core: core[i]
+        });
+    }
+    let mut central = degrees.clone();
+    central.sort_by(|a, b| b.pagerank.total_cmp(&a.pagerank).then_with(|| a.dataset.cmp(&b.dataset)));
+    central.truncate(top);
+    degrees.sort_by(|a, b| b.degree.cmp(&a.degree).then_with(|| a.dataset.cmp(&b.dataset)));
+    degrees.truncate(top);
+
+    let communities = community::louvain(graph);
+    let mut community_sizes = vec![0; community::count(&communities)];
+    for &c in communities.iter() {
+        community_sizes[c] += 1;
+    }
+
+    Statistics {
+        datasets: data.len(),
+        triples: data.values().map(|d| d.triples.get()).sum(),
+        links: data.values().flat_map(|d| d.links.iter()).map(|l| l.value.get()).sum(),
+        link_count: data.values().map(|d| d.links.len()).sum(),
+        domains,
+        identified,
+        vertices: graph.n,
+        edges: graph.edges().len(),
+        degree_distribution,
+        most_linked: degrees,
+        max_core: core.iter().cloned().max().unwrap_or(0),
+        most_central: central,
+        components: components(graph),
+        modularity: community::modularity(graph, &communities),
+        communities: community_sizes,
+        unlinked: graph::unlinked_datasets(data)
+    }
+}
+
+/// The sizes of the (weakly) connected components of the graph
+fn components(graph : &Graph) -> Vec<usize> {
+    let mut parent : Vec<usize> = (0..graph.n).collect();
+    fn find(parent : &mut [usize], mut i : usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for edge in graph.edges().iter() {
+        let a = find(&mut parent, edge.src);
+        let b = find(&mut parent, edge.trg);
+        if a != b {
+            parent[a] = b;
+        }
+    }
+    let mut sizes = HashMap::new();
+    for i in 0..graph.n {
+        *sizes.entry(find(&mut parent, i)).or_insert(0) += 1;
+    }
+    let mut sizes : Vec<usize> = sizes.into_values().collect();
+    sizes.sort_by(|a, b| b.cmp(a));
+    sizes
+}
+
+impl fmt::Display for Statistics {
+    /// Write the statistics as Markdown
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# LOD Cloud Statistics")?;
+        writeln!(f)?;
+        writeln!(f, "|                   | Count        |")?;
+        writeln!(f, "|-------------------|--------------|")?;
+        writeln!(f, "| Datasets          | {:>12} |", self.datasets)?;
+        writeln!(f, "| Triples           | {:>12} |", self.triples)?;
+        writeln!(f, "| Links             | {:>12} |", self.links)?;
+        writeln!(f, "| Linksets          | {:>12} |", self.link_count)?;
+        writeln!(f, "| Datasets in cloud | {:>12} |", self.vertices)?;
+        writeln!(f, "| Edges in cloud    | {:>12} |", self.edges)?;
+        writeln!(f, "| Without links     | {:>12} |", self.unlinked.len())?;
+        writeln!(f, "| Components        | {:>12} |", self.components.len())?;
+        writeln!(f, "| Largest k-core    | {:>12} |", self.max_core)?;
+        writeln!(f, "| Communities       | {:>12} |", self.communities.len())?;
+        writeln!(f)?;
+        writeln!(f, "## Domains")?;
+        writeln!(f)?;
+        writeln!(f, "| Domain            | Datasets   |")?;
+        writeln!(f, "|-------------------|------------|")?;
+        for (domain, count) in self.domains.iter() {
+            let domain = if domain.is_empty() { "(none)" } else { domain };
+            writeln!(f, "| {:<17} | {:>10} |", domain, count)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "{} dataset(s) had their domain identified automatically", self.identified)?;
+        writeln!(f)?;
+        writeln!(f, "## Most linked datasets")?;
+        writeln!(f)?;
+        writeln!(f, "| Dataset                          | In    | Out   | Total |")?;
+        writeln!(f, "|----------------------------------|-------|-------|-------|")?;
+        for entry in self.most_linked.iter() {
+            writeln!(f, "| {:<32} | {:>5} | {:>5} | {:>5} |",
+                     entry.title.as_ref().unwrap_or(&entry.dataset),
+                     entry.in_degree, entry.out_degree, entry.degree)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "## Most central datasets")?;
+        writeln!(f)?;
+        writeln!(f, "| Dataset                          | PageRank | Betweenness | Core  |")?;
+        writeln!(f, "|----------------------------------|----------|-------------|-------|")?;
+        for entry in self.most_central.iter() {
+            writeln!(f, "| {:<32} | {:>8.4} | {:>11.4} | {:>5} |",
+                     entry.title.as_ref().unwrap_or(&entry.dataset),
+                     entry.pagerank, entry.betweenness, entry.core)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "## Degree distribution")?;
+        writeln!(f)?;
+        writeln!(f, "| Degree | Datasets |")?;
+        writeln!(f, "|--------|----------|")?;
+        for (degree, count) in self.degree_distribution.iter() {
+            writeln!(f, "| {:>6} | {:>8} |", degree, count)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "## Connected components")?;
+        writeln!(f)?;
+        writeln!(f, "{}", self.components.iter().map(|c| c.to_string())
+                 .collect::<Vec<String>>().join(", "))?;
+        writeln!(f)?;
+        writeln!(f, "## Communities")?;
+        writeln!(f)?;
+        writeln!(f, "{}", self.communities.iter().map(|c| c.to_string())
+                 .collect::<Vec<String>>().join(", "))?;
+        writeln!(f)?;
+        writeln!(f, "Modularity: {:.3}", self.modularity)?;
+        writeln!(f)?;
+        writeln!(f, "## Datasets without links")?;
+        writeln!(f)?;
+        for dataset in self.unlinked.iter() {
+            writeln!(f, "* {}", dataset)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{Dataset, IntLike, Link};
+    use crate::graph::{Graph, Edge};
+    use crate::stats::*;
+
+    fn make_dataset(s : &str, d : &str, links : &[&str]) -> Dataset {
+        Dataset {
+            identifier: s.to_string(),
+            domain: d.to_string(),
+            triples: IntLike::from(100),
+            links: links.iter().map(|t| Link {
+                target: t.to_string(),
+                value: IntLike::from(5)
+            }).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_statistics() {
+        let mut data = HashMap::new();
+        data.insert("a".to_string(), make_dataset("a", "geography", &["b", "c"]));
+        data.insert("b".to_string(), make_dataset("b", "geography", &["a"]));
+        data.insert("c".to_string(), make_dataset("c", "media", &[]));
+        data.insert("d".to_string(), make_dataset("d", "media", &["e"]));
+        data.insert("e".to_string(), make_dataset("e", "", &[]));
+        data.insert("f".to_string(), make_dataset("f", "", &["x"]));
+
+        let mut g = Graph::new();
+        let a = g.add_vertex("a");
+        let b = g.add_vertex("b");
+        let c = g.add_vertex("c");
+        let d = g.add_vertex("d");
+        let e = g.add_vertex("e");
+        g.add_edge(Edge::new(a, b));
+        g.add_edge(Edge::new(a, c));
+        g.add_edge(Edge::new(b, a));
+        g.add_edge(Edge::new(d, e));
+
+        let stats = statistics(&data, &g, 1, 2);
+        assert_eq!(stats.triples, 600);
+        assert_eq!(stats.links, 25);
+        assert_eq!(stats.domains[""], 2);
+        assert_eq!(stats.components, vec![3, 2]);
+        assert_eq!(stats.communities, vec![3, 2]);
+        assert_eq!(stats.unlinked, vec!["f".to_string()]);
+        assert_eq!(stats.most_linked.len(), 2);
+        assert_eq!(stats.most_linked[0].dataset, "a");
+        assert_eq!(stats.most_linked[0].degree, 3);
+        assert_eq!(stats.degree_distribution[&1], 3);
+        assert_eq!(stats.max_core, 1);
+        assert_eq!(stats.most_central.len(), 2);
+        assert_eq!(stats.most_linked[0].core, 1);
+    }
+}
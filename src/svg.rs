@@ -1,38 +1,12 @@
 //! Methods for outputting graphs as SVG
-use crate::data::Dataset;
-use crate::graph::Graph;
-use htmlescape::encode_minimal;
-use noisy_float::prelude::*;
-use crate::settings::Settings;
-use std::cmp::{min, max};
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{Result,BufWriter,Write};
-use std::path::Path;
+use crate::label::LINE_SPACING;
+use crate::scene::{Bubble, Logo, Renderer, Scene, Weight, ARROW_LENGTH, LABEL_SIZE, LINE_HEIGHT, LETTER_WIDTH};
+use htmlescape::{encode_attribute, encode_minimal};
+use std::io::{Result,Write};
 
-const LETTER_WIDTH : usize = 17;
-const LINE_HEIGHT : usize = 42;
-
-/// Output a graph with a set of locations as an SVG file
-pub fn write_graph<P : AsRef<Path>>(graph : &Graph, loc : &Vec<f64>, 
-                                    data : &HashMap<String, Dataset>,
-                                    well_size : f64,
-                                    settings : &Settings, out_file : P) -> Result<()> {
-
-    let mut out = BufWriter::new(File::create(out_file)?);
-    let mut abs_max = list_abs_max(&loc) * 1.05;
-    if abs_max < well_size {
-        abs_max = well_size;
-    }
-    
-    writeln!(&mut out, "<svg
-    xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">",
-        (abs_max as usize) * 2, (abs_max as usize) * 2 + LINE_HEIGHT)?;
-    writeln!(&mut out, "{}", 
-//"  <script xmlns:xlink=\"http://www.w3.org/1999/xlink\" xlink:href=\"http://lod-cloud.net/versions/2017-08-22/SVGPan.js\"/>
-"<script type=\"text/javascript\"><![CDATA[
+const SCRIPT : &str = "<script type=\"text/javascript\"><![CDATA[
 function mo(elem) {    
-    var  line =document.getElementsByTagName(\"line\")
+    var  line =document.querySelectorAll(\".links > *\")
  
   elem.setAttribute(\"class\",\"circle-active\");
     for(i = 0; i < line.length; i++) {                                          
@@ -43,11 +17,11 @@ function mo(elem) {
 
         } 
         if(line[i].getAttributeNode(\"sourceId\").value === elem.id) {            
-            line[i].setAttribute(\"class\",\"link-activeOutgoung\");
+            line[i].setAttribute(\"class\",\"link-activeOutgoing\");
             document.getElementById(line[i].getAttributeNode(\"targetId\").value).setAttribute(\"class\",\"circle-active\");
 
         }
-        if((line[i].getAttributeNode(\"targetId\").value === elem.id)&&(line[i].getAttributeNode(\"sourceId\").value === elem.id)) {            
+        if(line[i].hasAttribute(\"bidirectional\")&&((line[i].getAttributeNode(\"targetId\").value === elem.id)||(line[i].getAttributeNode(\"sourceId\").value === elem.id))) {            
             line[i].setAttribute(\"class\",\"link-activeBoth\");
              document.getElementById(line[i].getAttributeNode(\"targetId\").value).setAttribute(\"class\",\"circle-active\");
              document.getElementById(line[i].getAttributeNode(\"sourceId\").value).setAttribute(\"class\",\"circle-active\");
@@ -57,21 +31,35 @@ function mo(elem) {
 
 function mleave(elem) {                   
     elem.setAttribute(\"class\",\"circle-pasive\");                                      
-    var line = document.getElementsByTagName(\"line\");                           
+    var line = document.querySelectorAll(\".links > *\");                           
     for(i = 0; i < line.length; i++) {                                          
                                                                                 
         if(line[i].getAttributeNode(\"targetId\").value === elem.id) {     
         document.getElementById(line[i].getAttributeNode(\"sourceId\").value).setAttribute(\"class\",\"circle-pasive\");       
-            line[i].setAttribute(\"class\",\"link\");       
+            line[i].setAttribute(\"class\",line[i].getAttribute(\"data-class\"));       
         }
         if(line[i].getAttributeNode(\"sourceId\").value === elem.id) {            
             document.getElementById(line[i].getAttributeNode(\"targetId\").value).setAttribute(\"class\",\"circle-pasive\");
-            line[i].setAttribute(\"class\",\"link\");       
+            line[i].setAttribute(\"class\",line[i].getAttribute(\"data-class\"));       
         }                                                                          
     }                                                                           
 }
-]]></script>
-  <style>
+]]></script>";
+
+/// The style of the light theme, which the other themes override
+const STYLE : &str = "  <style>
+    .background {
+        fill: none;
+    }
+    text {
+        font-family: Verdana, Arial, DejaVu Sans;
+    }
+    #legend text {
+        font-size: 200%;
+    }
+    #legend .title {
+        text-decoration: underline;
+    }
     circle { 
         stroke: #333;
         stroke-width: 1.5px;
@@ -81,6 +69,28 @@ function mleave(elem) {
         text-anchor: middle;
         font-size: .5em;
     }
+    .unavailable {
+        stroke: #d00000;
+        stroke-width: 3px;
+        stroke-dasharray: 6,4;
+    }
+    .availability-unknown {
+        stroke: #999;
+        stroke-dasharray: 2,3;
+    }
+    .added {
+        stroke: #1AC21D;
+        stroke-width: 5px;
+    }
+    .removed {
+        fill-opacity: 0.15;
+        stroke-opacity: 0.4;
+        stroke-dasharray: 4,4;
+    }
+    .changed {
+        stroke: #f6a000;
+        stroke-width: 4px;
+    }
     .circle-active circle { 
         stroke: #e80000;  
         stroke-width: 3px; 
@@ -92,169 +102,332 @@ function mleave(elem) {
         stroke-width: 1px;  
         stroke-dasharray:none;
     } 
+    .bidirectional {
+        stroke: #333;
+        stroke-width: 2.5px;
+    }
+    .links path {
+        fill: none;
+    }
+    .arrowhead {
+        fill: #555;
+    }
+    .link-added {
+        stroke: #1AC21D;
+        stroke-width: 2px;
+    }
+    .link-removed {
+        stroke: #be1b38;
+        stroke-width: 2px;
+        stroke-dasharray: 6,4;
+    }
+    .link-changed {
+        stroke: #f6a000;
+        stroke-width: 2px;
+    }
     .link-activeIncoming {  
-        stroke-opacity: 1; 
-        stroke-width: 10;  
+        stroke-opacity: 1 !important; 
+        stroke-width: 10 !important;  
         stroke: #be1b38; 
     } 
-    .link-activeOutgoung {  
-        stroke-opacity: 1;  
-        stroke-width: 10; 
+    .link-activeOutgoing {  
+        stroke-opacity: 1 !important;  
+        stroke-width: 10 !important; 
         stroke: #1AC21D;  
     } 
     .link-activeBoth {  
-        stroke-opacity: 1;  
+        stroke-opacity: 1 !important;  
         stroke: #1AC21D;  
-        stroke-width: 10;  
+        stroke-width: 10 !important;  
         stroke-dasharray:5,10,5;}
-  </style>")?;
-  writeln!(&mut out, 
-"  <g transform=\"scale({})\">", max(r64(0.5),r64(abs_max/1250.0)))?;
-  let leg_len = legend_length(settings);
-  writeln!(&mut out, "{}",
+  </style>";
+
+/// The built-in styles of the SVG output
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Default,Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    /// Dark links and outlines on a transparent background
+    #[default]
+    Light,
+    /// Light links, outlines and text on a dark background
+    Dark,
+    /// Black, thicker links and outlines and bold text on white
+    HighContrast,
+    /// Greyscale on white, for printing
+    Print
+}
+
+impl Theme {
+    /// The style overriding that of the light theme
+    fn style(&self) -> Option<&'static str> {
+        match self {
+            Theme::Light => None,
+            Theme::Dark => Some(include_str!("themes/dark.css")),
+            Theme::HighContrast => Some(include_str!("themes/high-contrast.css")),
+            Theme::Print => Some(include_str!("themes/print.css"))
+        }
+    }
+}
+
+/// A custom stylesheet applied after the theme
+#[derive(Clone,Debug,PartialEq)]
+pub enum Stylesheet {
+    /// CSS included in the image
+    Embedded(String),
+    /// The link of a CSS file imported by the image
+    Linked(String)
+}
+
+/// Writes scenes as SVG images, with a script highlighting the links of a
+/// bubble under the mouse
+#[derive(Clone,Debug,Default)]
+pub struct SvgRenderer {
+    pub theme : Theme,
+    pub stylesheet : Option<Stylesheet>
+}
+
+impl Renderer for SvgRenderer {
+    fn render(&self, scene : &Scene, out : &mut dyn Write) -> std::result::Result<(), String> {
+        write_svg(scene, self, out).map_err(|e| format!("Could not write SVG: {}", e))
+    }
+}
+
+/// Write a stylesheet as a `<style>` element
+fn write_style(css : &str, out : &mut dyn Write) -> Result<()> {
+    writeln!(out, "  <style><![CDATA[
+{}
+  ]]></style>", css.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+fn write_svg(scene : &Scene, renderer : &SvgRenderer, out : &mut dyn Write) -> Result<()> {
+    let size = scene.size as usize;
+    writeln!(out, "<svg
+    xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">",
+        size, size + LINE_HEIGHT as usize)?;
+    writeln!(out, "{}", SCRIPT)?;
+    writeln!(out, "{}", STYLE)?;
+    if let Some(style) = renderer.theme.style() {
+        write_style(style.trim_end(), out)?;
+    }
+    match renderer.stylesheet {
+        Some(Stylesheet::Embedded(ref css)) => write_style(css, out)?,
+        Some(Stylesheet::Linked(ref href)) => {
+            write_style(&format!("    @import url(\"{}\");", href.replace('"', "%22")), out)?
+        },
+        None => {}
+    }
+    writeln!(out, "  <rect class=\"background\" width=\"{}\" height=\"{}\"/>",
+             size, size + LINE_HEIGHT as usize)?;
+    if scene.arrows {
+        // The tip of the arrowhead is at the end of the link
+        writeln!(out, "  <defs>
+    <marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"{}\" markerHeight=\"{}\" markerUnits=\"userSpaceOnUse\" orient=\"auto-start-reverse\">
+      <path class=\"arrowhead\" d=\"M0,0 L10,5 L0,10 z\"/>
+    </marker>
+  </defs>", ARROW_LENGTH, ARROW_LENGTH)?;
+    }
+  writeln!(out, 
+"  <g transform=\"scale({})\">", scene.legend_scale())?;
+  let leg_len = scene.legend_width() as usize;
+  writeln!(out,
 "    <g id=\"legend\">
-      <text transform=\"translate(30,30)\" style=\"font-family:Verdana, Arial, DejaVu Sans;font-size:200%;text-decoration:underline;\">Legend</text>")?;
+      <text class=\"title\" transform=\"translate(30,30)\">Legend</text>")?;
   let mut i = 45;
-  for legend_entry in settings.legend.iter() {
-      writeln!(&mut out,
-"      <rect width=\"{}\" height=\"35\" style=\"fill:{}\" transform=\"translate(30,{})\"/>
-      <text transform=\"translate(35,{})\" style=\"font-family:Verdana, Arial;font-size:200%\">{}</text>", leg_len, legend_entry.colour, i, i + 27, legend_entry.title)?;
+  for legend_entry in scene.legend.iter() {
+      let class = Scene::availability_class(legend_entry.availability)
+          .map(|c| format!(" class=\"{}\"", c)).unwrap_or_default();
+      writeln!(out,
+"      <rect{} width=\"{}\" height=\"35\" style=\"fill:{}\" transform=\"translate(30,{})\"/>
+      <text transform=\"translate(35,{})\">{}</text>", class, leg_len, legend_entry.colour, i, i + 27, legend_entry.title)?;
       i += 40;
   }
-  writeln!(&mut out, "{}",
+  if !scene.weight_legend.is_empty() {
+      // The widths are kept as in the cloud, whatever the scale of the legend
+      writeln!(out,
+"      <text class=\"title\" transform=\"translate(30,{})\">Links</text>", i + 30)?;
+      i += 45;
+      for entry in scene.weight_legend.iter() {
+          writeln!(out,
+"      <line class=\"link\" x1=\"30\" y1=\"{}\" x2=\"130\" y2=\"{}\"{}/>
+      <text class=\"weight-label\" transform=\"translate(145,{})\">{}</text>", i + 17, i + 17,
+                   weight_style(&entry.weight, 1.0 / scene.legend_scale()), i + 27, entry.value)?;
+          i += 40;
+      }
+  }
+  writeln!(out,
 "    </g>
   </g>
   <g class=\"links\">")?;
 
-    eprintln!("Final well size: {:.3}", abs_max);
-    for edge in graph.edges.iter() {
-        writeln!(&mut out, "    <line class=\"link\" targetId=\"{}\" sourceId=\"{}\" x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>",
-                 edge.src, edge.trg, 
-                 loc[edge.src * 2] + abs_max,
-                 loc[edge.src * 2 + 1] + abs_max,
-                 loc[edge.trg * 2] + abs_max,
-                 loc[edge.trg * 2 + 1] + abs_max)?;
+    for line in scene.lines.iter() {
+        let class = format!("link{}{}",
+                            if line.is_bidirectional() { " bidirectional" } else { "" },
+                            line.highlight.as_ref().map(|h| format!(" {}", h)).unwrap_or_default());
+        let (element, geometry) = match line.control {
+            _ if !line.bundle.is_empty() => ("path", format!("d=\"M{:.2} {:.2}{}\"",
+                line.x1, line.y1,
+                line.bundle_curves().iter().map(|(_, (cx, cy), (x, y))| {
+                    format!(" Q{:.2} {:.2} {:.2} {:.2}", cx, cy, x, y)
+                }).collect::<String>())),
+            Some((cx, cy)) => ("path", format!("d=\"M{} {} Q{} {} {} {}\"",
+                                               line.x1, line.y1, cx, cy, line.x2, line.y2)),
+            None => ("line", format!("x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"",
+                                     line.x1, line.y1, line.x2, line.y2))
+        };
+        let markers = match (scene.arrows, line.is_bidirectional()) {
+            (false, _) => "",
+            (true, false) => " marker-end=\"url(#arrow)\"",
+            (true, true) => " marker-start=\"url(#arrow)\" marker-end=\"url(#arrow)\""
+        };
+        writeln!(out, "    <{} class=\"{}\" data-class=\"{}\"{} targetId=\"{}\" sourceId=\"{}\" {}{}{}/>",
+                 element, class, class,
+                 if line.is_bidirectional() { " bidirectional=\"true\"" } else { "" },
+                 scene.bubbles[line.target].vertex, scene.bubbles[line.source].vertex,
+                 geometry, weight_style(&line.weight, 1.0), markers)?;
     }
-    writeln!(&mut out, "  </g>
+    writeln!(out, "  </g>
   <g class=\"nodes\">")?;
-    
-    for i in 0..graph.n {
-        let dataset_name = graph.vertex_name(i).expect("Vertex name not in graph?!"); 
-        match data.get(&dataset_name) {
-            Some(dataset) => {
-                let title = dataset.title.clone()
-                    .unwrap_or_else(|| "Unnamed dataset".to_string());
-                writeln!(&mut out, 
+
+    for bubble in scene.bubbles.iter() {
+        writeln!(out, 
 "    <g id=\"{}\"
         onmouseover=\"mo(this)\" onmouseout=\"mleave(this)\">
-      <circle class=\"node\" r=\"{}\" cx=\"{}\" cy=\"{}\" fill=\"{}\"><title>{}</title></circle>
-      <a class=\"bubble\" href=\"https://lod-cloud.net/dataset/{}\" target=\"_blank\">
-          <text x=\"{}\" y=\"{}\">{}</text>
-      </a>
+      <circle class=\"node{}{}\" r=\"{:.1}\" cx=\"{}\" cy=\"{}\" fill=\"{}\"><title>{}</title></circle>
+      {}
     </g>",
-                 i,
-                 bubble_size(dataset, settings.bubble_size_factor.unwrap_or(10.0)),
-                 loc[i * 2] + abs_max,
-                 loc[i * 2 + 1] + abs_max,
-                 get_colour(&dataset.domain, &dataset.keywords, settings), 
-                 dataset.identifier,
-                 dataset.identifier,
-                 loc[i * 2] + abs_max,
-                 loc[i * 2 + 1] + abs_max,
-                 encode_minimal(&shorten_text(&title)))?;
-            },
-            None => {
-                eprintln!("Dataset not in set: {} (maybe `identifier` is incorrect?)", 
-                          &dataset_name);
-            }
-        }
+                 bubble.vertex,
+                 Scene::availability_class(bubble.availability)
+                     .map(|c| format!(" {}", c)).unwrap_or_default(),
+                 bubble.highlight.as_ref()
+                     .map(|h| format!(" {}", h)).unwrap_or_default(),
+                 bubble.r,
+                 bubble.x,
+                 bubble.y,
+                 bubble.colour, 
+                 bubble.identifier,
+                 label(bubble))?;
     }
 
-    match settings.rights_text {
-        Some(ref rt) =>
-            writeln!(&mut out,
+    if let Some(ref rt) = scene.rights_text {
+        writeln!(out,
                      "    <g transform=\"translate(20,{}) scale({})\">
-      <text style=\"font-family: Verdana, Arial;\">{}</text>
+      <text>{}</text>
     </g>",
-    (abs_max as usize) * 2 + LETTER_WIDTH, 
-    min(r64(abs_max * 3.0 / ((rt.len() + 1) as f64) / (LETTER_WIDTH as f64)), r64(1.0)),
-    rt)?,
-        None => {}
-    };
+    size + LETTER_WIDTH as usize, 
+    scene.rights_scale(),
+    rt)?;
+    }
 
-    match settings.logo_link {
-        Some(ref l) => {
-            writeln!(&mut out,
+    match scene.logo {
+        Logo::Image { ref href, width } => {
+            writeln!(out,
                      "    <image x=\"{}\" y=\"{}\" height=\"{}\" href=\"{}\"/>",
-                     (abs_max as usize) * 2 - settings.logo_width.unwrap_or(120),
-                     (abs_max as usize) * 2, LINE_HEIGHT, l)?;
+                     size - width, size, LINE_HEIGHT, href)?;
         },
-        None => {
-            writeln!(&mut out,
+        Logo::Default => {
+            writeln!(out,
                      "      <g transform=\"translate({}, {})\">",
-                     (abs_max as usize) * 2 - 120,
-                     (abs_max as usize) * 2)?;
-            writeln!(&mut out,
+                     size - crate::scene::LOGO_WIDTH, size)?;
+            writeln!(out,
                      include_str!("by.svg"))?;
-            writeln!(&mut out,
+            writeln!(out,
                      "      </g>")?;
         }
     };
 
-    writeln!(&mut out, "  </g>
+    writeln!(out, "  </g>
 </svg>")
 }
 
-fn list_abs_max(xs : &Vec<f64>) -> f64 {
-    let mut max = 0.0;
-    for x in xs {
-        if x.is_finite() && x.abs() > max {
-            max = x.abs()
-        }
+/// The style of a weighted link, with its width multiplied by `scale`, which
+/// overrides that of its classes
+fn weight_style(weight : &Weight, scale : f64) -> String {
+    let mut style = Vec::new();
+    if let Some(width) = weight.width {
+        style.push(format!("stroke-width:{:.2}px", width * scale));
     }
-    max
-}
-
-fn get_colour(domain : &str, keywords : &Vec<String>, settings : &Settings) -> String {
-    for e in settings.legend.iter() {
-        if let Some(ref d) = e.domain {
-            if domain == d {
-                return e.colour.to_string()
-            }
-        }
-        if let Some(ref tags) = e.keywords {
-            for t in tags.iter() {
-                if keywords.contains(t) {
-                    return e.colour.to_string()
-                }
-            }
-            if tags.len() == 0 {
-                return e.colour.to_string()
-            }
-        }
+    if let Some(opacity) = weight.opacity {
+        style.push(format!("stroke-opacity:{:.2}", opacity));
     }
-    "white".to_string()
-}
-
-fn shorten_text(text : &str) -> String {
-    if text.len() > 9 {
-        let mut s = text.chars().take(6).collect::<String>();
-        s.push_str("...");
-        s
+    if style.is_empty() {
+        String::new()
     } else {
-        text.to_string()
+        format!(" style=\"{}\"", style.join(";"))
     }
 }
 
-fn bubble_size(dataset : &Dataset, factor : f64) -> String {
-    let size = (dataset.triples.get() as f64) + 1.0;
-    format!("{:.1}", 15.0 + size.log(factor))
+/// The label of a bubble, as a link to the page of its dataset if it has one
+fn label(bubble : &Bubble) -> String {
+    let text = match bubble.label.as_slice() {
+        [] => return String::new(),
+        [line] if bubble.label_size == LABEL_SIZE => {
+            format!("<text x=\"{}\" y=\"{}\">{}</text>", bubble.x, bubble.y, encode_minimal(line))
+        },
+        lines => {
+            let spacing = bubble.label_size * LINE_SPACING;
+            let top = bubble.y - (lines.len() - 1) as f64 * spacing / 2.0;
+            // The size is relative to the .5em of the style
+            let style = if bubble.label_size == LABEL_SIZE {
+                String::new()
+            } else {
+                format!(" style=\"font-size:{:.3}em\"", 0.5 * bubble.label_size / LABEL_SIZE)
+            };
+            format!("<text x=\"{}\" y=\"{}\"{}>{}</text>",
+                    bubble.x, top, style,
+                    lines.iter().enumerate().map(|(n, line)| {
+                        format!("<tspan x=\"{}\" y=\"{}\">{}</tspan>",
+                                bubble.x, top + n as f64 * spacing, encode_minimal(line))
+                    }).collect::<String>())
+        }
+    };
+    match bubble.url {
+        Some(ref url) => format!("<a class=\"bubble\" href=\"{}\" target=\"_blank\">
+          {}
+      </a>", encode_attribute(url), text),
+        None => format!("<g class=\"bubble\">
+          {}
+      </g>", text)
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::svg::*;
 
-fn legend_length(settings : &Settings) -> usize {
-    let m = settings.legend.iter().map(|le| {
-        le.title.len() * LETTER_WIDTH
-    }).max().unwrap_or(0);
-    max(m, 310)
+    fn render(renderer : &SvgRenderer) -> String {
+        let scene = Scene {
+            size: 100.0,
+            bubbles: Vec::new(),
+            lines: Vec::new(),
+            legend: Vec::new(),
+            rights_text: None,
+            logo: Logo::Image { href: "logo.png".to_string(), width: 50 },
+            arrows: false,
+            weight_legend: Vec::new()
+        };
+        let mut out = Vec::new();
+        renderer.render(&scene, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_themes() {
+        let light = render(&SvgRenderer::default());
+        assert!(light.contains("class=\"background\""));
+        assert!(!light.contains("CDATA[\n    .background"));
+        let dark = render(&SvgRenderer { theme: Theme::Dark, stylesheet: None });
+        assert!(dark.contains("fill: #1b1d23;"));
+        let custom = render(&SvgRenderer {
+            theme: Theme::Print,
+            stylesheet: Some(Stylesheet::Embedded(".link > x { stroke: red }]]>".to_string()))
+        });
+        assert!(custom.find("grayscale").unwrap() < custom.find(".link > x").unwrap());
+        assert!(custom.contains("]]]]><![CDATA[>"));
+        let linked = render(&SvgRenderer {
+            theme: Theme::Light,
+            stylesheet: Some(Stylesheet::Linked("brand.css".to_string()))
+        });
+        assert!(linked.contains("@import url(\"brand.css\");"));
+        assert_eq!(serde_json::from_str::<Theme>("\"high-contrast\"").unwrap(), Theme::HighContrast);
+    }
 }
@@ -0,0 +1,189 @@
+//! Methods for outputting graphs as TikZ pictures, in a standalone LaTeX
+//! document that can be compiled as is or included in another document. The
+//! logo is left out
+use crate::label::LINE_SPACING;
+use crate::scene::{Renderer, Scene, Weight, ARROW_LENGTH, LETTER_WIDTH, LINE_HEIGHT};
+use std::io::{Result, Write};
+use std::str::FromStr;
+
+/// The size of a pixel of the SVG output in points
+const PT_PER_PX : f64 = 0.75;
+
+/// Writes scenes as standalone LaTeX documents with a TikZ picture
+pub struct TikzRenderer;
+
+impl Renderer for TikzRenderer {
+    fn render(&self, scene : &Scene, out : &mut dyn Write) -> std::result::Result<(), String> {
+        write_tikz(scene, out).map_err(|e| format!("Could not write TikZ: {}", e))
+    }
+}
+
+fn write_tikz(scene : &Scene, out : &mut dyn Write) -> Result<()> {
+    // Each colour is defined once and named by its index
+    let mut colours : Vec<&str> = Vec::new();
+    for colour in scene.legend.iter().map(|e| e.colour.as_str())
+        .chain(scene.bubbles.iter().map(|b| b.colour.as_str())) {
+        if !colours.contains(&colour) {
+            colours.push(colour);
+        }
+    }
+    let colour_name = |colour : &str| {
+        format!("cloud{}", colours.iter().position(|c| *c == colour).unwrap_or(0))
+    };
+
+    writeln!(out, "\\documentclass[tikz]{{standalone}}
+\\usepackage[T1]{{fontenc}}
+\\usepackage[utf8]{{inputenc}}
+\\usetikzlibrary{{arrows.meta}}")?;
+    for colour in colours.iter() {
+        writeln!(out, "\\definecolor{{{}}}{{HTML}}{{{}}}", colour_name(colour), hex(colour))?;
+    }
+    writeln!(out, "\\begin{{document}}
+\\begin{{tikzpicture}}[x={}pt, y=-{}pt,
+    link/.style={{draw=black, opacity=0.3, line width=0.5}},
+    bidirectional/.style={{line width=1}},
+    link-added/.style={{draw=green!70!black, opacity=0.8, line width=1.5}},
+    link-removed/.style={{draw=red, opacity=0.6, dashed}},
+    link-changed/.style={{draw=orange, opacity=0.8, line width=1.5}},
+    bubble/.style={{draw=black, line width=0.5}},
+    unavailable/.style={{draw=red, dashed, line width=1}},
+    availability-unknown/.style={{draw=gray, dotted, line width=1}},
+    added/.style={{draw=green!70!black, line width=2}},
+    removed/.style={{opacity=0.3}},
+    changed/.style={{draw=orange, line width=2}},
+    bubble-label/.style={{inner sep=0, align=center}},
+    link-arrow/.style={{-{{Stealth[length={len}pt, width={len}pt]}}}},
+    link-arrow-both/.style={{{{Stealth[length={len}pt, width={len}pt]}}-{{Stealth[length={len}pt, width={len}pt]}}}}]",
+             PT_PER_PX, PT_PER_PX, len = ARROW_LENGTH * PT_PER_PX)?;
+    writeln!(out, "\\useasboundingbox (0,0) rectangle ({},{});",
+             scene.size, scene.size + LINE_HEIGHT)?;
+
+    for line in scene.lines.iter() {
+        let mut styles = vec!["link".to_string()];
+        if line.is_bidirectional() {
+            styles.push("bidirectional".to_string());
+        }
+        styles.extend(line.highlight.iter().map(|h| h.to_string()));
+        styles.extend(weight_styles(&line.weight));
+        if scene.arrows {
+            styles.push(if line.is_bidirectional() { "link-arrow-both" } else { "link-arrow" }.to_string());
+        }
+        let mut curves = line.bundle_curves();
+        if let Some(control) = line.control {
+            curves.push(((line.x1, line.y1), control, (line.x2, line.y2)));
+        }
+        // A quadratic curve is the cubic curve with control points two
+        // thirds of the way to its control point
+        let path = if curves.is_empty() {
+            format!("-- ({:.2},{:.2})", line.x2, line.y2)
+        } else {
+            curves.iter().map(|&((x1, y1), (cx, cy), (x2, y2))| {
+                format!(".. controls ({:.2},{:.2}) and ({:.2},{:.2}) .. ({:.2},{:.2})",
+                        x1 + 2.0 / 3.0 * (cx - x1), y1 + 2.0 / 3.0 * (cy - y1),
+                        x2 + 2.0 / 3.0 * (cx - x2), y2 + 2.0 / 3.0 * (cy - y2), x2, y2)
+            }).collect::<Vec<_>>().join(" ")
+        };
+        writeln!(out, "\\draw[{}] ({:.2},{:.2}) {};",
+                 styles.join(", "), line.x1, line.y1, path)?;
+    }
+
+    for bubble in scene.bubbles.iter() {
+        let mut styles = vec!["bubble".to_string(), format!("fill={}", colour_name(&bubble.colour))];
+        styles.extend(Scene::availability_class(bubble.availability).map(|c| c.to_string()));
+        styles.extend(bubble.highlight.iter().map(|h| h.to_string()));
+        writeln!(out, "\\filldraw[{}] ({:.2},{:.2}) circle[radius={:.1}];",
+                 styles.join(", "), bubble.x, bubble.y, bubble.r)?;
+        if !bubble.label.is_empty() {
+            let lines : Vec<String> = bubble.label.iter().map(|l| escape(l)).collect();
+            writeln!(out, "\\node[bubble-label, font=\\fontsize{{{:.2}}}{{{:.2}}}\\selectfont] at ({:.2},{:.2}) {{{}}};",
+                     bubble.label_size * PT_PER_PX, bubble.label_size * PT_PER_PX * LINE_SPACING,
+                     bubble.x, bubble.y, lines.join("\\\\ "))?;
+        }
+    }
+
+    // The legend, in the top left corner as in the SVG output
+    let legend_scale = scene.legend_scale();
+    let legend_width = scene.legend_width();
+    if !scene.legend.is_empty() || !scene.weight_legend.is_empty() {
+        writeln!(out, "\\begin{{scope}}[scale={}, every node/.style={{anchor=base west, inner sep=0, font=\\fontsize{{{}}}{{{}}}\\selectfont}}]",
+                 legend_scale, 24.0 * PT_PER_PX * legend_scale, 28.0 * PT_PER_PX * legend_scale)?;
+        writeln!(out, "\\node at (30,30) {{\\underline{{Legend}}}};")?;
+        for (n, entry) in scene.legend.iter().enumerate() {
+            let y = 45.0 + 40.0 * n as f64;
+            let mut styles = vec![format!("fill={}", colour_name(&entry.colour))];
+            styles.extend(Scene::availability_class(entry.availability).map(|c| c.to_string()));
+            writeln!(out, "\\path[{}] (30,{}) rectangle ({},{});",
+                     styles.join(", "), y, 30.0 + legend_width, y + 35.0)?;
+            writeln!(out, "\\node at (35,{}) {{{}}};", y + 27.0, escape(&entry.title))?;
+        }
+        // The link weights below the entries, whose widths are not scaled
+        if !scene.weight_legend.is_empty() {
+            let top = 45.0 + 40.0 * scene.legend.len() as f64;
+            writeln!(out, "\\node at (30,{}) {{\\underline{{Links}}}};", top + 30.0)?;
+            for (n, entry) in scene.weight_legend.iter().enumerate() {
+                let y = top + 45.0 + 40.0 * n as f64;
+                let mut styles = vec!["link".to_string()];
+                styles.extend(weight_styles(&entry.weight));
+                writeln!(out, "\\draw[{}] (30,{}) -- (130,{});", styles.join(", "), y + 17.0, y + 17.0)?;
+                writeln!(out, "\\node at (145,{}) {{{}}};", y + 27.0, entry.value)?;
+            }
+        }
+        writeln!(out, "\\end{{scope}}")?;
+    }
+
+    if let Some(ref rt) = scene.rights_text {
+        let scale = scene.rights_scale();
+        writeln!(out, "\\node[anchor=base west, inner sep=0, font=\\fontsize{{{}}}{{{}}}\\selectfont] at (20,{}) {{{}}};",
+                 16.0 * PT_PER_PX * scale, 19.0 * PT_PER_PX * scale,
+                 scene.size + LETTER_WIDTH, escape(rt))?;
+    }
+
+    writeln!(out, "\\end{{tikzpicture}}
+\\end{{document}}")
+}
+
+/// The line width and opacity of a weighted link
+fn weight_styles(weight : &Weight) -> Vec<String> {
+    weight.width.map(|w| format!("line width={:.2}pt", w * PT_PER_PX)).into_iter()
+        .chain(weight.opacity.map(|o| format!("opacity={:.2}", o)))
+        .collect()
+}
+
+/// The six hexadecimal digits of a CSS colour, or white if it is not valid
+fn hex(colour : &str) -> String {
+    svgtypes::Color::from_str(colour)
+        .map(|c| format!("{:02X}{:02X}{:02X}", c.red, c.green, c.blue))
+        .unwrap_or_else(|_| "FFFFFF".to_string())
+}
+
+/// Escape the characters that have a special meaning in LaTeX
+fn escape(text : &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => result.push_str("\\textbackslash{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                result.push('\\');
+                result.push(c);
+            },
+            '~' => result.push_str("\\textasciitilde{}"),
+            '^' => result.push_str("\\textasciicircum{}"),
+            '\n' => result.push(' '),
+            c => result.push(c)
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tikz::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("R&D 100% #1 {a_b}"), "R\\&D 100\\% \\#1 \\{a\\_b\\}");
+        assert_eq!(escape("~^\\"), "\\textasciitilde{}\\textasciicircum{}\\textbackslash{}");
+        assert_eq!(hex("#29c9cc"), "29C9CC");
+        assert_eq!(hex("white"), "FFFFFF");
+    }
+}
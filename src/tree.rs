@@ -3,7 +3,7 @@
 //! 1. Find the most connected central node.
 //! 2. Fan all nodes connected to the centre in a 360° arc.
 //! 3. Recursively fan each other node out in a 180° arc.
-use crate::graph::Graph;
+use crate::graph::{Direction, Edge, Graph};
 use std::f64::consts::PI;
 use std::collections::HashSet;
 
@@ -19,10 +19,10 @@ use std::collections::HashSet;
 /// let v3 = g.add_vertex("v3");
 /// let v4 = g.add_vertex("v4");
 /// let v5 = g.add_vertex("v5");
-/// g.edges.push(Edge::new(v1, v2));
-/// g.edges.push(Edge::new(v1, v3));
-/// g.edges.push(Edge::new(v1, v4));
-/// g.edges.push(Edge::new(v4, v5));
+/// g.add_edge(Edge::new(v1, v2));
+/// g.add_edge(Edge::new(v1, v3));
+/// g.add_edge(Edge::new(v1, v4));
+/// g.add_edge(Edge::new(v4, v5));
 ///
 /// let result = build_tree(&g, 10.0);
 /// ```
@@ -34,20 +34,14 @@ pub fn build_tree(graph : &Graph, radius : f64) -> Vec<f64> {
     let mut nodes = HashSet::new();
     nodes.extend(0..graph.n);
 
-    let mut edges = Vec::new();
-    edges.resize(graph.n, Vec::new());
-
-    for edge in graph.edges.iter() {
-        edges[edge.src].push(edge.trg);
-        edges[edge.trg].push(edge.src);
-    }
+    let edges = graph.adjacency(Direction::Both);
 
     let mut v0 = 0;
     let mut max_edges = 0;
-    for i in 0..graph.n {
-        if edges[i].len() > max_edges {
+    for (i, e) in edges.iter().enumerate() {
+        if e.len() > max_edges {
             v0 = i;
-            max_edges = edges[i].len();
+            max_edges = e.len();
         }
     }
 
@@ -56,11 +50,63 @@ pub fn build_tree(graph : &Graph, radius : f64) -> Vec<f64> {
     let mut loc = Vec::new();
     loc.resize(graph.n * 2, 0.0f64);
     
-    calculate_loc(&mut loc, v0, &mut nodes, &edges, graph, radius);
+    calculate_loc(&mut loc, v0, &mut nodes, &edges, radius);
 
     push_nearby(loc, radius)
 }
 
+/// Builds a layout where each community (as given by the community of each
+/// vertex) is laid out as a separate tree. The largest community is placed in
+/// the centre and the others on a ring around it.
+pub fn community_layout(graph : &Graph, communities : &[usize], radius : f64) -> Vec<f64> {
+    let n_communities = communities.iter().max().map(|c| c + 1).unwrap_or(0);
+    let mut members = vec![Vec::new(); n_communities];
+    let mut index = vec![0; graph.n];
+    for (i, &c) in communities.iter().enumerate() {
+        index[i] = members[c].len();
+        members[c].push(i);
+    }
+
+    let mut trees = Vec::new();
+    for (c, vertices) in members.iter().enumerate() {
+        let mut sub = Graph::new();
+        for &v in vertices.iter() {
+            sub.add_vertex(&graph.vertex_name(v).expect("Vertex name not in graph?!"));
+        }
+        for edge in graph.edges().iter() {
+            if communities[edge.src] == c && communities[edge.trg] == c {
+                sub.add_edge(Edge::new(index[edge.src], index[edge.trg]));
+            }
+        }
+        let loc = build_tree(&sub, radius);
+        trees.push((max_radius(&loc) + radius, loc));
+    }
+
+    let ring_radius = if trees.len() > 1 {
+        let outer = trees[1..].iter().map(|t| t.0).fold(0.0, f64::max);
+        let circumference : f64 = trees[1..].iter().map(|t| 2.0 * t.0).sum();
+        (trees[0].0 + outer).max(circumference / (2.0 * PI))
+    } else {
+        0.0
+    };
+    let astep = 2.0 * PI / ((trees.len().max(2) - 1) as f64);
+
+    let mut loc = vec![0.0; graph.n * 2];
+    for (c, (_, tree)) in trees.iter().enumerate() {
+        let (cx, cy) = if c == 0 {
+            (0.0, 0.0)
+        } else {
+            (ring_radius * (astep * (c - 1) as f64).cos(),
+             ring_radius * (astep * (c - 1) as f64).sin())
+        };
+        for (j, &v) in members[c].iter().enumerate() {
+            loc[v * 2] = cx + tree[j * 2];
+            loc[v * 2 + 1] = cy + tree[j * 2 + 1];
+        }
+    }
+    loc
+}
+
 fn push_nearby(mut loc : Vec<f64>, radius : f64) -> Vec<f64> {
     for i in 0..(loc.len() / 2) {
         for j in 0..(loc.len() / 2) {
@@ -91,9 +137,7 @@ fn to_angle(x : f64, y : f64) -> f64 {
             0.0
         }
     } else if x < 0.0 {
-        if y > 0.0 {
-            PI + (y/x).atan()
-        } else if y < 0.0 {
+        if y != 0.0 {
             PI + (y/x).atan()
         } else {
             PI
@@ -113,7 +157,7 @@ fn to_angle(x : f64, y : f64) -> f64 {
 
 
 fn calculate_loc(loc : &mut Vec<f64>, parent : usize, nodes : &mut HashSet<usize>,
-                 edges : &Vec<Vec<usize>>, graph : &Graph, radius : f64) {
+                 edges : &Vec<Vec<usize>>, radius : f64) {
 
     let children : Vec<&usize> = edges[parent].iter().
         filter(|x| nodes.contains(x)).collect();
@@ -141,7 +185,7 @@ fn calculate_loc(loc : &mut Vec<f64>, parent : usize, nodes : &mut HashSet<usize
     let new_children = pseudo_shuffle(children);
 
     for &child in new_children.iter() {
-        calculate_loc(loc, *child, nodes, edges, graph, radius);
+        calculate_loc(loc, *child, nodes, edges, radius);
     }
 
 }
@@ -152,24 +196,102 @@ fn pseudo_shuffle(mut init : Vec<&usize>) -> Vec<&usize> {
     let mut i = 0;
     while !init.is_empty() {
         new_list.push(init.remove(i));
-        if init.len() % 13 == 0 {
+        if init.len().is_multiple_of(13) {
             i += 7
         } else {
             i += 13
         }
-        if init.len() > 0 {
-            i = i % init.len();
+        if !init.is_empty() {
+            i %= init.len();
         }
     }
     new_list
 }
 
+/// How the datasets without links are arranged around the cloud
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnlinkedLayout {
+    /// On concentric rings around the cloud
+    Ring,
+    /// In a grid below the cloud
+    Grid
+}
+
+/// Place `n` extra bubbles on concentric rings around the bubbles in `loc`,
+/// with `distance` between the bubbles and between the rings. The return value
+/// is the list of [x0,y0,...,xn,yn] of the new bubbles only.
+pub fn ring_layout(loc : &[f64], n : usize, distance : f64) -> Vec<f64> {
+    let mut radius = max_radius(loc) + distance;
+    let mut result = Vec::new();
+    while result.len() < n * 2 {
+        let remaining = n - result.len() / 2;
+        let capacity = ((2.0 * PI * radius / distance).floor() as usize).max(1);
+        let on_ring = remaining.min(capacity);
+        let astep = 2.0 * PI / (on_ring as f64);
+        for i in 0..on_ring {
+            result.push(radius * (astep * i as f64).cos());
+            result.push(radius * (astep * i as f64).sin());
+        }
+        radius += distance;
+    }
+    result
+}
+
+/// Place `n` extra bubbles in a grid below the bubbles in `loc`, with 
+/// `distance` between the bubbles. The return value is the list of 
+/// [x0,y0,...,xn,yn] of the new bubbles only.
+pub fn grid_layout(loc : &[f64], n : usize, distance : f64) -> Vec<f64> {
+    let width = max_radius(loc).max(distance);
+    let columns = ((2.0 * width / distance).floor() as usize).max(1);
+    let top = loc.iter().skip(1).step_by(2).cloned()
+        .filter(|y| y.is_finite())
+        .fold(0.0f64, f64::max) + 2.0 * distance;
+    let mut result = Vec::new();
+    for i in 0..n {
+        result.push(-width + distance / 2.0 + ((i % columns) as f64) * distance);
+        result.push(top + ((i / columns) as f64) * distance);
+    }
+    result
+}
+
+fn max_radius(loc : &[f64]) -> f64 {
+    let mut max = 0.0f64;
+    for i in 0..(loc.len() / 2) {
+        let d = (loc[i * 2] * loc[i * 2] + loc[i * 2 + 1] * loc[i * 2 + 1]).sqrt();
+        if d.is_finite() && d > max {
+            max = d;
+        }
+    }
+    max
+}
+
 #[cfg(test)]
 mod tests {
-    use graph::{Graph, Edge};
-    use tree::{build_tree, to_angle, push_nearby};
+    use crate::graph::{Graph, Edge};
+    use crate::tree::{build_tree, to_angle, push_nearby, ring_layout, grid_layout, community_layout};
     use std::f64::consts::PI;
 
+    #[test]
+    fn test_community_layout() {
+        let mut g = Graph::new();
+        for i in 0..6 {
+            g.add_vertex(&i.to_string());
+        }
+        g.add_edge(Edge::new(0, 1));
+        g.add_edge(Edge::new(1, 2));
+        g.add_edge(Edge::new(2, 3));
+        g.add_edge(Edge::new(4, 5));
+        let loc = community_layout(&g, &[0, 0, 0, 1, 1, 1], 10.0);
+        assert_eq!(loc.len(), 12);
+        assert!(loc.iter().all(|x| x.is_finite()));
+        // The first community is centred on its most connected vertex
+        assert_eq!((loc[2], loc[3]), (0.0, 0.0));
+        // The second community is placed away from the first
+        let d = ((loc[8] - loc[2]).powi(2) + (loc[9] - loc[3]).powi(2)).sqrt();
+        assert!(d > 20.0);
+    }
+
     #[test]
     #[allow(non_snake_case)]
     fn test_angle() {
@@ -219,10 +341,10 @@ mod tests {
         let v3 = g.add_vertex("v3");
         let v4 = g.add_vertex("v4");
         let v5 = g.add_vertex("v5");
-        g.edges.push(Edge::new(v1, v2));
-        g.edges.push(Edge::new(v1, v3));
-        g.edges.push(Edge::new(v1, v4));
-        g.edges.push(Edge::new(v4, v5));
+        g.add_edge(Edge::new(v1, v2));
+        g.add_edge(Edge::new(v1, v3));
+        g.add_edge(Edge::new(v1, v4));
+        g.add_edge(Edge::new(v4, v5));
 
         let result = build_tree(&g, 10.0);
 
@@ -238,4 +360,24 @@ mod tests {
             assert!((exp[i] - result[i]).abs() < 1e-4);
         } 
     }
+
+    #[test]
+    fn test_ring_layout() {
+        let loc = [0.0, 0.0, 30.0, 40.0];
+        let result = ring_layout(&loc, 40, 10.0);
+        assert_eq!(result.len(), 80);
+        for i in 0..40 {
+            let d = (result[i * 2] * result[i * 2] + result[i * 2 + 1] * result[i * 2 + 1]).sqrt();
+            assert!(d >= 60.0 - 1e-4);
+        }
+        // The first ring of radius 60 has space for 37 bubbles
+        assert!((result[74] - 70.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_grid_layout() {
+        let loc = [0.0, 0.0, 0.0, 20.0];
+        let result = grid_layout(&loc, 5, 10.0);
+        assert_eq!(result, vec![-15.0, 40.0, -5.0, 40.0, 5.0, 40.0, 15.0, 40.0, -15.0, 50.0]);
+    }
 }